@@ -0,0 +1,8 @@
+-- Moderation events in Discord guilds, see src/audit.rs
+CREATE TABLE audit_log (
+    guild BIGINT NOT NULL,
+    timestamp TIMESTAMP WITH TIME ZONE NOT NULL,
+    event JSONB NOT NULL
+);
+
+CREATE INDEX audit_log_timestamp ON audit_log (timestamp);
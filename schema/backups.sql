@@ -0,0 +1,14 @@
+-- Minecraft world backups created by wurstminebot, see src/backup.rs
+CREATE TABLE backups (
+    id SERIAL PRIMARY KEY,
+    world TEXT NOT NULL,
+    started_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    finished_at TIMESTAMP WITH TIME ZONE,
+    size BIGINT,
+    error TEXT,
+    created_by BIGINT,
+    reason TEXT NOT NULL,
+    pruned BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX backups_world_started_at ON backups (world, started_at);
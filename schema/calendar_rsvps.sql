@@ -0,0 +1,6 @@
+-- Discord users who RSVP'd to a calendar event using `/event rsvp`
+CREATE TABLE calendar_rsvps (
+    event INTEGER NOT NULL REFERENCES calendar (id) ON DELETE CASCADE,
+    snowflake BIGINT NOT NULL,
+    PRIMARY KEY (event, snowflake)
+);
@@ -0,0 +1,8 @@
+-- Discord membership changes (joins, leaves, roles) of people in the people database
+CREATE TABLE member_history (
+    snowflake BIGINT NOT NULL,
+    timestamp TIMESTAMP WITH TIME ZONE NOT NULL,
+    event JSONB NOT NULL
+);
+
+CREATE INDEX member_history_snowflake ON member_history (snowflake, timestamp);
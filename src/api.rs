@@ -81,7 +81,11 @@ use {
     },
     rocket_ws::WebSocket,
     serde::Serialize,
-    serenity::model::prelude::*,
+    serenity::{
+        all::Context as DiscordCtx,
+        model::prelude::*,
+    },
+    serenity_utils::RwFuture,
     sqlx::{
         PgPool,
         types::Json as PgJson,
@@ -93,7 +97,10 @@ use {
             AsyncReadExt as _,
         },
         select,
-        sync::mpsc,
+        sync::{
+            mpsc,
+            watch,
+        },
         time::{
             sleep,
             timeout,
@@ -112,6 +119,7 @@ use {
         ClientMessage,
         ServerMessageV3,
         ServerMessageV4,
+        VoiceChannel,
     },
     crate::{
         BASE_PATH,
@@ -134,6 +142,7 @@ use {
             User,
             UserParam,
         },
+        voice::VoiceState,
     },
 };
 #[cfg(not(target_os = "linux"))] use crate::systemd_minecraft;
@@ -705,11 +714,39 @@ pub(crate) async fn player_head(db_pool: &State<PgPool>, http_client: &State<req
 
 #[derive(Serialize)]
 pub(crate) struct WorldInfo {
-    main: bool,
-    running: bool,
-    version: Option<String>,
+    pub(crate) main: bool,
+    pub(crate) running: bool,
+    pub(crate) version: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    list: Option<Vec<user::Id>>,
+    pub(crate) list: Option<Vec<user::Id>>,
+}
+
+impl WorldInfo {
+    /// Gets the status of the given world, including the list of online players.
+    pub(crate) async fn with_players(db_pool: &PgPool, world: &systemd_minecraft::World) -> Result<Self, Error> {
+        Ok(Self {
+            main: *world == systemd_minecraft::World::default(),
+            running: world.is_running().await?,
+            version: world.version().await?,
+            list: Some(match world.ping().await {
+                Ok(ping) => {
+                    let sample = ping.sample.unwrap_or_default();
+                    let mut list = Vec::with_capacity(sample.len());
+                    for player in sample {
+                        let uuid = player.id.parse()?;
+                        list.push(
+                            User::from_minecraft_uuid(db_pool, uuid).await?
+                                .ok_or_else(|| Error::UnknownMinecraftUuid(uuid))?
+                                .id
+                        );
+                    }
+                    list
+                }
+                Err(craftping::Error::Io(e)) if e.kind() == io::ErrorKind::ConnectionRefused => Vec::default(),
+                Err(e) => return Err(e.into()),
+            }),
+        })
+    }
 }
 
 #[rocket::get("/api/<version>/server/worlds.json")]
@@ -732,28 +769,7 @@ pub(crate) async fn worlds_with_players(db_pool: &State<PgPool>, version: Versio
     let _ /* no version differences */ = ActiveVersion::try_from(version)?;
     stream::iter(systemd_minecraft::World::all().await?)
         .map(Ok)
-        .and_then(async |world| Ok((world.to_string(), WorldInfo {
-            main: world == systemd_minecraft::World::default(),
-            running: world.is_running().await?,
-            version: world.version().await?,
-            list: Some(match world.ping().await {
-                Ok(ping) => {
-                    let sample = ping.sample.unwrap_or_default();
-                    let mut list = Vec::with_capacity(sample.len());
-                    for player in sample {
-                        let uuid = player.id.parse()?;
-                        list.push(
-                            User::from_minecraft_uuid(&**db_pool, uuid).await?
-                                .ok_or_else(|| Error::UnknownMinecraftUuid(uuid))?
-                                .id
-                        );
-                    }
-                    list
-                }
-                Err(craftping::Error::Io(e)) if e.kind() == io::ErrorKind::ConnectionRefused => Vec::default(),
-                Err(e) => return Err(e.into()),
-            }),
-        })))
+        .and_then(async |world| Ok((world.to_string(), WorldInfo::with_players(db_pool, &world).await?)))
         .try_collect().await
         .map(Json)
 }
@@ -835,28 +851,7 @@ pub(crate) async fn player_data_json(db_pool: &State<PgPool>, version: Version,
 #[rocket::get("/api/<version>/world/<world>/status.json")]
 pub(crate) async fn world_status(db_pool: &State<PgPool>, version: Version, world: systemd_minecraft::World) -> Result<Json<WorldInfo>, StatusOrError<Error>> {
     let _ /* no version differences */ = ActiveVersion::try_from(version)?;
-    Ok(Json(WorldInfo {
-        main: world == systemd_minecraft::World::default(),
-        running: world.is_running().await?,
-        version: world.version().await?,
-        list: Some(match world.ping().await {
-            Ok(ping) => {
-                let sample = ping.sample.unwrap_or_default();
-                let mut list = Vec::with_capacity(sample.len());
-                for player in sample {
-                    let uuid = player.id.parse()?;
-                    list.push(
-                        User::from_minecraft_uuid(&**db_pool, uuid).await?
-                            .ok_or_else(|| Error::UnknownMinecraftUuid(uuid))?
-                            .id
-                    );
-                }
-                list
-            }
-            Err(craftping::Error::Io(e)) if e.kind() == io::ErrorKind::ConnectionRefused => Vec::default(),
-            Err(e) => return Err(e.into()),
-        }),
-    }))
+    Ok(Json(WorldInfo::with_players(db_pool, &world).await?))
 }
 
 type WsStream = SplitStream<rocket_ws::stream::DuplexStream>;
@@ -941,6 +936,14 @@ impl ActiveVersion {
             Self::V4 => lock!(sink = sink; ServerMessageV4::BlockEntities { dimension, cx, cz, data }.write_ws024(&mut *sink).await),
         }
     }
+
+    async fn write_voice_state(&self, sink: &WsSink, channels: Vec<VoiceChannel>) -> Result<(), async_proto::WriteError> {
+        println!("sending voice state to WebSocket client ({self:?})");
+        match self {
+            Self::V3 => unreachable!("voice state subscriptions are rejected in API version 3"),
+            Self::V4 => lock!(sink = sink; ServerMessageV4::VoiceState { channels }.write_ws024(&mut *sink).await),
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -971,7 +974,7 @@ impl From<Vec<notify::Error>> for WsError {
     }
 }
 
-async fn client_session(db_pool: PgPool, mut rocket_shutdown: rocket::Shutdown, version: ActiveVersion, stream: WsStream, sink: WsSink) -> Result<(), WsError> {
+async fn client_session(db_pool: PgPool, discord_ctx: RwFuture<DiscordCtx>, authorized: bool, mut rocket_shutdown: rocket::Shutdown, version: ActiveVersion, stream: WsStream, sink: WsSink) -> Result<(), WsError> {
     #[derive(Default, Clone, Copy)]
     struct Subscriptions {
         block_states: bool,
@@ -1142,6 +1145,7 @@ async fn client_session(db_pool: PgPool, mut rocket_shutdown: rocket::Shutdown,
     let players_cache = Mutex::default();
     let (watch_tx, mut watch_rx) = mpsc::channel(1_024);
     let watcher = Mutex::new(notify::recommended_watcher(move |res| watch_tx.blocking_send(res).allow_unreceived())?);
+    let mut voice_state = None::<watch::Receiver<Option<Vec<VoiceChannel>>>>;
     let mut read = pin!(timeout(Duration::from_mins(1), ClientMessage::read_ws_owned024(stream)));
     loop {
         select! {
@@ -1174,8 +1178,29 @@ async fn client_session(db_pool: PgPool, mut rocket_shutdown: rocket::Shutdown,
                         println!("WebSocket client ({version:?}) subscribed to block entities for chunk column {cx} {cz} ({dimension:?})");
                         update_chunks(version, &main_world, &region_cache, &watcher, &sink, iter::once((dimension, cx, 0, cz)), ChunkUpdateReason::SubscribeBlockEntities).await?;
                     }
+                    ClientMessage::SubscribeToVoiceState => if !authorized {
+                        version.write_custom_error(&sink, "SubscribeToVoiceState", "voice state requires an API key").await?;
+                    } else if let ActiveVersion::V3 = version {
+                        version.write_custom_error(&sink, "SubscribeToVoiceState", "voice state requires API version 4 or later").await?;
+                    } else if voice_state.is_none() {
+                        println!("WebSocket client ({version:?}) subscribed to voice state");
+                        let mut rx = discord_ctx.read().await.data.read().await.get::<VoiceState>().expect("missing voice state").subscribe();
+                        let channels = rx.borrow_and_update().clone();
+                        voice_state = Some(rx);
+                        if let Some(channels) = channels {
+                            version.write_voice_state(&sink, channels).await?;
+                        }
+                    },
                 }
             }
+            res = async { voice_state.as_mut().expect("checked by select precondition").changed().await }, if voice_state.is_some() => if res.is_ok() {
+                let channels = voice_state.as_mut().expect("checked by select precondition").borrow_and_update().clone();
+                if let Some(channels) = channels {
+                    version.write_voice_state(&sink, channels).await?;
+                }
+            } else {
+                voice_state = None; // Discord bot has shut down
+            },
             Some(res) = watch_rx.recv() => {
                 let mut paths = HashSet::new();
                 let event = res?;
@@ -1225,9 +1250,11 @@ async fn client_session(db_pool: PgPool, mut rocket_shutdown: rocket::Shutdown,
 }
 
 #[rocket::get("/api/<version>/websocket")]
-pub(crate) fn websocket(db_pool: &State<PgPool>, me: Option<User>, uri: Origin<'_>, ws: request::Outcome<WebSocket, Never>, shutdown: rocket::Shutdown, version: Version) -> Result<Either<rocket_ws::Channel<'static>, (Status, RawHtml<String>)>, Status> {
+pub(crate) fn websocket(db_pool: &State<PgPool>, discord_ctx: &State<RwFuture<DiscordCtx>>, me: Option<User>, uri: Origin<'_>, ws: request::Outcome<WebSocket, Never>, shutdown: rocket::Shutdown, version: Version) -> Result<Either<rocket_ws::Channel<'static>, (Status, RawHtml<String>)>, Status> {
     let version = ActiveVersion::try_from(version)?;
     let db_pool = (**db_pool).clone();
+    let discord_ctx = (**discord_ctx).clone();
+    let authorized = me.is_some();
     Ok(match ws {
         Outcome::Success(ws) => Either::Left(ws.channel(move |stream| Box::pin(async move {
             let (ws_sink, ws_stream) = stream.split();
@@ -1248,7 +1275,7 @@ pub(crate) fn websocket(db_pool: &State<PgPool>, me: Option<User>, uri: Origin<'
                 }),
             };
             println!("start of WebSocket client session ({version:?})");
-            if let Err(e) = client_session(db_pool, shutdown, version, ws_stream, ws_sink.clone()).await {
+            if let Err(e) = client_session(db_pool, discord_ctx, authorized, shutdown, version, ws_stream, ws_sink.clone()).await {
                 println!("WebSocket client session errored: {e}");
                 println!("debug info: {e:?}");
                 let _ = lock!(ws_sink = ws_sink; match version {
@@ -0,0 +1,369 @@
+use {
+    chrono::prelude::*,
+    rocket::{
+        State,
+        http::Status,
+        response::content::RawHtml,
+    },
+    rocket_util::{
+        Origin,
+        ToHtml,
+        html,
+    },
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    serenity::{
+        all::{
+            CreateEmbed,
+            CreateMessage,
+            MessageBuilder,
+        },
+        model::{
+            guild::audit_log::{
+                Action,
+                MemberAction,
+            },
+            prelude::*,
+        },
+        prelude::*,
+    },
+    sqlx::{
+        PgPool,
+        types::Json,
+    },
+    crate::{
+        config::Config,
+        discord::{
+            DbPool,
+            PgSnowflake,
+        },
+        http::{
+            PageStyle,
+            Tab,
+            page,
+        },
+        member::RoleChanges,
+        time::{
+            DateTimeFormat,
+            format_datetime,
+        },
+        user::User,
+    },
+};
+
+#[derive(Debug, thiserror::Error, rocket_util::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Serenity(#[from] serenity::Error),
+    #[error(transparent)] Sql(#[from] sqlx::Error),
+}
+
+/// A moderation event in a Discord guild, as recorded in the `audit_log` table.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub(crate) enum AuditEvent {
+    /// The content is the edited version since the original isn't available without a message cache.
+    MessageEdited {
+        channel: ChannelId,
+        message: MessageId,
+        author: UserId,
+        content: String,
+    },
+    MessageDeleted {
+        channel: ChannelId,
+        message: MessageId,
+    },
+    /// The moderator and reason are `None` if the ban couldn't be found in the Discord audit log.
+    Banned {
+        user: UserId,
+        username: String,
+        by: Option<UserId>,
+        reason: Option<String>,
+    },
+    Kicked {
+        user: UserId,
+        username: String,
+        by: UserId,
+        reason: Option<String>,
+    },
+    NicknameChanged {
+        user: UserId,
+        old: Option<String>,
+        new: Option<String>,
+    },
+    RoleAdded {
+        user: UserId,
+        role: RoleId,
+        name: String,
+    },
+    RoleRemoved {
+        user: UserId,
+        role: RoleId,
+        name: String,
+    },
+}
+
+impl AuditEvent {
+    fn title(&self) -> &'static str {
+        match self {
+            Self::MessageEdited { .. } => "Message edited",
+            Self::MessageDeleted { .. } => "Message deleted",
+            Self::Banned { .. } => "Member banned",
+            Self::Kicked { .. } => "Member kicked",
+            Self::NicknameChanged { .. } => "Nickname changed",
+            Self::RoleAdded { .. } => "Role added",
+            Self::RoleRemoved { .. } => "Role removed",
+        }
+    }
+
+    fn colour(&self) -> Colour {
+        match self {
+            Self::MessageEdited { .. } | Self::NicknameChanged { .. } => Colour::BLUE,
+            Self::MessageDeleted { .. } | Self::RoleRemoved { .. } => Colour::ORANGE,
+            Self::Banned { .. } | Self::Kicked { .. } => Colour::RED,
+            Self::RoleAdded { .. } => Colour::DARK_GREEN,
+        }
+    }
+
+    fn discord_embed(&self, guild_id: GuildId) -> CreateEmbed {
+        let mut description = MessageBuilder::default();
+        match self {
+            Self::MessageEdited { channel, message, author, content } => {
+                description.mention(author);
+                description.push(" edited ");
+                description.push(message.link(*channel, Some(guild_id)));
+                description.push_line(":");
+                description.push_quote_safe(content);
+            }
+            Self::MessageDeleted { channel, message } => {
+                description.push(format!("message {message} was deleted from "));
+                description.mention(channel);
+            }
+            Self::Banned { user, username, by, reason } => {
+                description.mention(user);
+                description.push(" (");
+                description.push_safe(username);
+                description.push(") was banned");
+                if let Some(by) = by {
+                    description.push(" by ");
+                    description.mention(by);
+                }
+                if let Some(reason) = reason {
+                    description.push_line("");
+                    description.push("reason: ");
+                    description.push_safe(reason);
+                }
+            }
+            Self::Kicked { user, username, by, reason } => {
+                description.mention(user);
+                description.push(" (");
+                description.push_safe(username);
+                description.push(") was kicked by ");
+                description.mention(by);
+                if let Some(reason) = reason {
+                    description.push_line("");
+                    description.push("reason: ");
+                    description.push_safe(reason);
+                }
+            }
+            Self::NicknameChanged { user, old, new } => {
+                description.mention(user);
+                description.push(" changed their nickname from ");
+                description.push_mono_safe(old.as_deref().unwrap_or("(none)"));
+                description.push(" to ");
+                description.push_mono_safe(new.as_deref().unwrap_or("(none)"));
+            }
+            Self::RoleAdded { user, name, .. } => {
+                description.mention(user);
+                description.push(" got the ");
+                description.push_safe(name);
+                description.push(" role");
+            }
+            Self::RoleRemoved { user, name, .. } => {
+                description.mention(user);
+                description.push(" lost the ");
+                description.push_safe(name);
+                description.push(" role");
+            }
+        }
+        CreateEmbed::new()
+            .colour(self.colour())
+            .title(self.title())
+            .description(description.build())
+    }
+
+    async fn to_html(&self, db_pool: &PgPool, guild_id: GuildId) -> sqlx::Result<RawHtml<String>> {
+        Ok(match self {
+            Self::MessageEdited { channel, message, author, content } => html! {
+                : user_html(db_pool, *author).await?;
+                : " edited ";
+                a(href = message.link(*channel, Some(guild_id))) : "a message";
+                : ": ";
+                q : content;
+            },
+            Self::MessageDeleted { channel, message } => html! {
+                : "message ";
+                : message.to_string();
+                : " was deleted from ";
+                a(href = format!("https://discord.com/channels/{guild_id}/{channel}")) : "a channel";
+            },
+            Self::Banned { user, username, by, reason } => html! {
+                : user_html(db_pool, *user).await?;
+                : " (";
+                : username;
+                : ") was banned";
+                @if let Some(by) = by {
+                    : " by ";
+                    : user_html(db_pool, *by).await?;
+                }
+                @if let Some(reason) = reason {
+                    : " (reason: ";
+                    : reason;
+                    : ")";
+                }
+            },
+            Self::Kicked { user, username, by, reason } => html! {
+                : user_html(db_pool, *user).await?;
+                : " (";
+                : username;
+                : ") was kicked by ";
+                : user_html(db_pool, *by).await?;
+                @if let Some(reason) = reason {
+                    : " (reason: ";
+                    : reason;
+                    : ")";
+                }
+            },
+            Self::NicknameChanged { user, old, new } => html! {
+                : user_html(db_pool, *user).await?;
+                : " changed their nickname from ";
+                code : old.as_deref().unwrap_or("(none)");
+                : " to ";
+                code : new.as_deref().unwrap_or("(none)");
+            },
+            Self::RoleAdded { user, name, .. } => html! {
+                : user_html(db_pool, *user).await?;
+                : " got the ";
+                : name;
+                : " role";
+            },
+            Self::RoleRemoved { user, name, .. } => html! {
+                : user_html(db_pool, *user).await?;
+                : " lost the ";
+                : name;
+                : " role";
+            },
+        })
+    }
+}
+
+/// Links to the person's profile if they're in the people database, or shows their Discord user ID otherwise.
+async fn user_html(db_pool: &PgPool, user_id: UserId) -> sqlx::Result<RawHtml<String>> {
+    Ok(if let Some(user) = User::from_discord(db_pool, user_id).await? {
+        user.to_html()
+    } else {
+        html! {
+            : user_id.to_string();
+        }
+    })
+}
+
+/// Stores the event in the database and posts it to the guild's audit channel. Does nothing if moderation logging is disabled for this guild.
+pub(crate) async fn record(ctx: &Context, guild_id: GuildId, event: AuditEvent) -> Result<(), Error> {
+    let (audit_channel, pool) = {
+        let data = ctx.data.read().await;
+        let Some(audit_channel) = data.get::<Config>().expect("missing config").wurstminebot.guild(guild_id).and_then(|guild| guild.audit_channel) else { return Ok(()) };
+        (audit_channel, data.get::<DbPool>().expect("missing database connection").clone())
+    };
+    let embed = event.discord_embed(guild_id);
+    // record the event first so it's not lost if posting it fails
+    sqlx::query!("INSERT INTO audit_log (guild, timestamp, event) VALUES ($1, $2, $3)", PgSnowflake(guild_id) as _, Utc::now(), Json(event) as _).execute(&pool).await?;
+    audit_channel.send_message(ctx, CreateMessage::new().embed(embed)).await?;
+    Ok(())
+}
+
+/// Records nickname and role changes of the given member compared to the given person's Discord data in the people database. Must be called before that data is updated.
+///
+/// Without a member cache, the previous state is only known for people in the database.
+pub(crate) async fn record_member_update(ctx: &Context, member: &Member, person: &User, role_changes: &RoleChanges) -> Result<(), Error> {
+    let Some(ref discorddata) = person.discorddata else { return Ok(()) };
+    if discorddata.nick != member.nick {
+        record(ctx, member.guild_id, AuditEvent::NicknameChanged { user: member.user.id, old: discorddata.nick.clone(), new: member.nick.clone() }).await?;
+    }
+    for (role, name) in &role_changes.added {
+        record(ctx, member.guild_id, AuditEvent::RoleAdded { user: member.user.id, role: *role, name: name.clone() }).await?;
+    }
+    for (role, name) in &role_changes.removed {
+        record(ctx, member.guild_id, AuditEvent::RoleRemoved { user: member.user.id, role: *role, name: name.clone() }).await?;
+    }
+    Ok(())
+}
+
+/// Records a ban, looking up the moderator and reason in the guild's Discord audit log.
+pub(crate) async fn record_ban(ctx: &Context, guild_id: GuildId, user_id: UserId, username: String) -> Result<(), Error> {
+    if ctx.data.read().await.get::<Config>().expect("missing config").wurstminebot.guild(guild_id).and_then(|guild| guild.audit_channel).is_none() { return Ok(()) }
+    let entry = guild_id.audit_logs(ctx, Some(Action::Member(MemberAction::BanAdd)), None, None, Some(10)).await?.entries.into_iter().find(|entry| entry.target_id.is_some_and(|target_id| target_id.get() == user_id.get()));
+    record(ctx, guild_id, AuditEvent::Banned {
+        user: user_id,
+        username,
+        by: entry.as_ref().map(|entry| entry.user_id),
+        reason: entry.and_then(|entry| entry.reason),
+    }).await
+}
+
+/// Checks the guild's Discord audit log to find out whether the given user was kicked, rather than leaving on their own, and records it if so.
+///
+/// Discord doesn't send an event for kicks, so this relies on the audit log entry being recent. Bans are recorded by [`record_ban`] instead.
+pub(crate) async fn record_member_removal(ctx: &Context, guild_id: GuildId, user_id: UserId) -> Result<(), Error> {
+    if ctx.data.read().await.get::<Config>().expect("missing config").wurstminebot.guild(guild_id).and_then(|guild| guild.audit_channel).is_none() { return Ok(()) }
+    let Some(entry) = guild_id.audit_logs(ctx, Some(Action::Member(MemberAction::Kick)), None, None, Some(10)).await?.entries.into_iter().find(|entry|
+        entry.target_id.is_some_and(|target_id| target_id.get() == user_id.get())
+        && Utc::now().timestamp() - entry.id.created_at().unix_timestamp() < 60 // ignore earlier kicks of the same user
+    ) else { return Ok(()) };
+    let username = user_id.to_user(ctx).await?.name;
+    record(ctx, guild_id, AuditEvent::Kicked { user: user_id, username, by: entry.user_id, reason: entry.reason }).await
+}
+
+#[rocket::get("/admin/audit")]
+pub(crate) async fn get(db_pool: &State<PgPool>, config: &State<Config>, me: Option<User>, uri: Origin<'_>) -> Result<(Status, RawHtml<String>), Error> {
+    if !me.as_ref().is_some_and(|me| me.is_discord_admin(config)) {
+        return Ok((Status::Forbidden, page(&me, &uri, PageStyle::default(), "Forbidden — Wurstmineberg", Tab::None, html! {
+            h1 : "Error 403: Forbidden";
+            p : "The moderation log is only available to admins.";
+        })))
+    }
+    let entries = sqlx::query!(r#"SELECT guild AS "guild: PgSnowflake<GuildId>", timestamp AS "timestamp: DateTime<Utc>", event AS "event: Json<AuditEvent>" FROM audit_log ORDER BY timestamp DESC LIMIT 500"#).fetch_all(&**db_pool).await?;
+    let multiple_guilds = !config.wurstminebot.other_guilds.is_empty();
+    Ok((Status::Ok, page(&me, &uri, PageStyle::default(), "Moderation log — Wurstmineberg", Tab::None, html! {
+        h1 : "Moderation log";
+        @if entries.is_empty() {
+            p : "No moderation events have been recorded yet.";
+        } else {
+            table(class = "table table-responsive") {
+                thead {
+                    tr {
+                        th : "Time";
+                        @if multiple_guilds {
+                            th : "Guild";
+                        }
+                        th : "Event";
+                        th : "Details";
+                    }
+                }
+                tbody {
+                    @for entry in entries {
+                        tr {
+                            td : format_datetime(entry.timestamp, DateTimeFormat { long: false, running_text: false });
+                            @if multiple_guilds {
+                                td : entry.guild.0.to_string();
+                            }
+                            td : entry.event.0.title();
+                            td : entry.event.0.to_html(db_pool, entry.guild.0).await?;
+                        }
+                    }
+                }
+            }
+        }
+    })))
+}
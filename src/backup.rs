@@ -0,0 +1,400 @@
+use {
+    std::{
+        io,
+        path::{
+            Path,
+            PathBuf,
+        },
+    },
+    chrono::{
+        TimeDelta,
+        prelude::*,
+    },
+    rocket::{
+        State,
+        http::Status,
+        response::content::RawHtml,
+    },
+    rocket_util::{
+        Origin,
+        html,
+    },
+    serenity::{
+        all::{
+            ButtonStyle,
+            ComponentInteraction,
+            CreateActionRow,
+            CreateButton,
+            CreateInteractionResponse,
+            CreateInteractionResponseMessage,
+            MessageBuilder,
+        },
+        model::prelude::*,
+        prelude::*,
+    },
+    sqlx::PgPool,
+    tokio::{
+        process::Command,
+        sync::Mutex,
+    },
+    wheel::{
+        fs,
+        traits::{
+            AsyncCommandOutputExt as _,
+            IoResultExt as _,
+        },
+    },
+    crate::{
+        BASE_PATH,
+        config::Config,
+        discord::{
+            DbPool,
+            PgSnowflake,
+        },
+        http::{
+            PageStyle,
+            Tab,
+            page,
+        },
+        time::{
+            DateTimeFormat,
+            format_datetime,
+        },
+        user::User,
+    },
+};
+#[cfg(not(target_os = "linux"))] use crate::systemd_minecraft;
+
+/// Prefix of the custom IDs of the buttons for confirming a restore, followed by the backup ID.
+const RESTORE_CUSTOM_ID_PREFIX: &str = "restore:";
+
+/// Held while a backup is being created or restored, so a world isn't archived while it's being replaced.
+static LOCK: Mutex<()> = Mutex::const_new(());
+
+#[derive(Debug, thiserror::Error, rocket_util::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Minecraft(#[from] systemd_minecraft::Error),
+    #[error(transparent)] Serenity(#[from] serenity::Error),
+    #[error(transparent)] Sql(#[from] sqlx::Error),
+    #[error(transparent)] Wheel(#[from] wheel::Error),
+    #[error("failed to restore backup ({restore}) and to restart the world afterwards ({start})")]
+    RestoreAndStart {
+        restore: Box<Error>,
+        start: systemd_minecraft::Error,
+    },
+    #[error("no world named {0}")]
+    UnknownWorld(String),
+}
+
+/// A backup of a Minecraft world, as recorded in the `backups` table.
+pub(crate) struct Backup {
+    pub(crate) id: i32,
+    world: String,
+    started_at: DateTime<Utc>,
+    /// `None` while the backup is still being created.
+    finished_at: Option<DateTime<Utc>>,
+    /// Size of the archive in bytes.
+    size: Option<i64>,
+    error: Option<String>,
+    created_by: Option<PgSnowflake<UserId>>,
+    reason: String,
+    /// Whether the archive has been deleted because it was older than the retention period.
+    pruned: bool,
+}
+
+impl Backup {
+    pub(crate) async fn from_id(pool: &PgPool, id: i32) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as!(Self, r#"SELECT id, world, started_at AS "started_at: DateTime<Utc>", finished_at AS "finished_at: DateTime<Utc>", size, error, created_by AS "created_by: PgSnowflake<UserId>", reason, pruned FROM backups WHERE id = $1"#, id).fetch_optional(pool).await
+    }
+
+    /// The most recent backups, newest first, optionally only of the given world.
+    pub(crate) async fn recent(pool: &PgPool, world: Option<&systemd_minecraft::World>, limit: i64) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as!(Self, r#"SELECT id, world, started_at AS "started_at: DateTime<Utc>", finished_at AS "finished_at: DateTime<Utc>", size, error, created_by AS "created_by: PgSnowflake<UserId>", reason, pruned FROM backups WHERE $1::TEXT IS NULL OR world = $1 ORDER BY started_at DESC LIMIT $2"#, world.map(|world| world.to_string()), limit).fetch_all(pool).await
+    }
+
+    fn path(&self) -> PathBuf {
+        archive_path(&self.world, self.id)
+    }
+
+    fn is_restorable(&self) -> bool {
+        self.finished_at.is_some() && self.error.is_none() && !self.pruned
+    }
+
+    fn status(&self) -> String {
+        if let Some(ref error) = self.error {
+            format!("failed: {error}")
+        } else if self.pruned {
+            format!("pruned")
+        } else if self.finished_at.is_none() {
+            format!("in progress")
+        } else {
+            self.size.map_or_else(|| format!("done"), format_size)
+        }
+    }
+
+    /// A one-line summary for Discord messages.
+    pub(crate) fn discord_line(&self) -> String {
+        MessageBuilder::default()
+            .push(format!("`{}` ", self.id))
+            .push_safe(&self.world)
+            .push(format!(" <t:{}:f> ", self.started_at.timestamp()))
+            .push_safe(&self.reason)
+            .push(" — ")
+            .push_safe(self.status())
+            .build()
+    }
+}
+
+fn archive_path(world: &str, id: i32) -> PathBuf {
+    Path::new(BASE_PATH).join("backup").join(world).join(format!("{id}.tar.gz"))
+}
+
+fn format_size(bytes: i64) -> String {
+    let bytes = bytes as f64;
+    if bytes >= 1024.0 * 1024.0 * 1024.0 {
+        format!("{:.1} GiB", bytes / (1024.0 * 1024.0 * 1024.0))
+    } else {
+        format!("{:.1} MiB", bytes / (1024.0 * 1024.0))
+    }
+}
+
+/// Archives the world directory. The server is told to stop saving while the archive is written, so the files are consistent.
+async fn create_locked(pool: &PgPool, world: &systemd_minecraft::World, created_by: Option<UserId>, reason: &str) -> Result<i32, Error> {
+    let id = sqlx::query_scalar!("INSERT INTO backups (world, started_at, created_by, reason) VALUES ($1, $2, $3, $4) RETURNING id", world.to_string(), Utc::now(), created_by.map(PgSnowflake) as _, reason).fetch_one(pool).await?;
+    let path = archive_path(&world.to_string(), id);
+    let result = async {
+        let running = world.is_running().await?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        if running {
+            world.command("save-off").await?;
+        }
+        let archived = async {
+            if running {
+                world.command("save-all flush").await?;
+            }
+            Command::new("tar").arg("-czf").arg(&path).arg("-C").arg(world.dir()).arg(".").check("tar").await?;
+            Ok::<_, Error>(())
+        }.await;
+        // always turn saving back on, even if the backup failed
+        if running {
+            world.command("save-on").await?;
+        }
+        archived?;
+        Ok::<_, Error>(tokio::fs::metadata(&path).await.at(&path)?.len())
+    }.await;
+    match result {
+        Ok(size) => {
+            sqlx::query!("UPDATE backups SET finished_at = $1, size = $2 WHERE id = $3", Utc::now(), size as i64, id).execute(pool).await?;
+            Ok(id)
+        }
+        Err(e) => {
+            sqlx::query!("UPDATE backups SET finished_at = $1, error = $2 WHERE id = $3", Utc::now(), e.to_string(), id).execute(pool).await?;
+            Err(e)
+        }
+    }
+}
+
+/// Creates a backup of the given world and returns its ID.
+pub(crate) async fn create(ctx: &Context, world: &systemd_minecraft::World, created_by: Option<UserId>, reason: &str) -> Result<i32, Error> {
+    let pool = ctx.data.read().await.get::<DbPool>().expect("missing database connection").clone();
+    let _lock = LOCK.lock().await;
+    create_locked(&pool, world, created_by, reason).await
+}
+
+/// Replaces the world directory with the contents of the given backup, stopping the world while doing so.
+///
+/// The current state of the world is backed up first, so a restore can be undone. Returns the ID of that backup.
+/// The backup is extracted next to the world directory and only swapped in once extraction succeeded, and the world is started again afterwards even if the restore failed.
+pub(crate) async fn restore(ctx: &Context, backup: &Backup, restored_by: Option<UserId>) -> Result<i32, Error> {
+    let pool = ctx.data.read().await.get::<DbPool>().expect("missing database connection").clone();
+    let Some(world) = systemd_minecraft::World::all().await?.into_iter().find(|world| world.to_string() == backup.world) else {
+        return Err(Error::UnknownWorld(backup.world.clone()))
+    };
+    let _lock = LOCK.lock().await;
+    let safety_backup = create_locked(&pool, &world, restored_by, &format!("before restoring backup {}", backup.id)).await?;
+    let running = world.is_running().await?;
+    if running {
+        world.stop().await?;
+    }
+    let restored = async {
+        let dir = world.dir();
+        let staging = dir.with_file_name(format!("{world}.restoring"));
+        let replaced = dir.with_file_name(format!("{world}.replaced"));
+        for leftover in [&staging, &replaced] { // from an earlier restore that was interrupted
+            match fs::remove_dir_all(leftover).await {
+                Ok(()) => {}
+                Err(wheel::Error::Io { inner, .. }) if inner.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        fs::create_dir_all(&staging).await?;
+        Command::new("tar").arg("-xzf").arg(backup.path()).arg("-C").arg(&staging).check("tar").await?;
+        fs::rename(&dir, &replaced).await?;
+        if let Err(e) = fs::rename(&staging, &dir).await {
+            fs::rename(&replaced, &dir).await?; // put the current state back
+            return Err(e.into())
+        }
+        fs::remove_dir_all(&replaced).await?;
+        Ok::<_, Error>(())
+    }.await;
+    let started = if running { world.start().await } else { Ok(()) };
+    match (restored, started) {
+        (Ok(()), Ok(())) => Ok(safety_backup),
+        (Err(e), Ok(())) => Err(e),
+        (Ok(()), Err(e)) => Err(e.into()),
+        (Err(restore), Err(start)) => Err(Error::RestoreAndStart { restore: Box::new(restore), start }),
+    }
+}
+
+/// Deletes the archives of backups older than the configured retention period, keeping the latest successful backup of each world.
+pub(crate) async fn prune(ctx: &Context) -> Result<(), Error> {
+    let (pool, retention_days) = {
+        let data = ctx.data.read().await;
+        (data.get::<DbPool>().expect("missing database connection").clone(), data.get::<Config>().expect("missing config").wurstminebot.backup_retention_days)
+    };
+    let Some(retention_days) = retention_days else { return Ok(()) };
+    let cutoff = Utc::now() - TimeDelta::days(retention_days.into());
+    let _lock = LOCK.lock().await;
+    let expired = sqlx::query!(r#"SELECT id, world FROM backups WHERE started_at < $1 AND finished_at IS NOT NULL AND NOT pruned AND id NOT IN (SELECT DISTINCT ON (world) id FROM backups WHERE error IS NULL AND finished_at IS NOT NULL ORDER BY world, started_at DESC)"#, cutoff).fetch_all(&pool).await?;
+    for backup in expired {
+        match fs::remove_file(archive_path(&backup.world, backup.id)).await {
+            Ok(()) => {}
+            Err(wheel::Error::Io { inner, .. }) if inner.kind() == io::ErrorKind::NotFound => {} // e.g. a failed backup which didn't create an archive
+            Err(e) => return Err(e.into()),
+        }
+        sqlx::query!("UPDATE backups SET pruned = TRUE WHERE id = $1", backup.id).execute(&pool).await?;
+    }
+    Ok(())
+}
+
+/// Asks the admin to confirm restoring the given backup, returning the message to show them.
+pub(crate) async fn confirm_restore_message(ctx: &Context, id: i32) -> Result<CreateInteractionResponseMessage, Error> {
+    let pool = ctx.data.read().await.get::<DbPool>().expect("missing database connection").clone();
+    Ok(match Backup::from_id(&pool, id).await? {
+        Some(backup) if backup.is_restorable() => CreateInteractionResponseMessage::new()
+            .ephemeral(true)
+            .content(MessageBuilder::default()
+                .push("Restore this backup? The ")
+                .push_safe(&backup.world)
+                .push(" world will be stopped if it's running, and its current state will be backed up first.\n")
+                .push(backup.discord_line())
+                .build()
+            )
+            .components(vec![CreateActionRow::Buttons(vec![
+                CreateButton::new(format!("{RESTORE_CUSTOM_ID_PREFIX}{id}")).style(ButtonStyle::Danger).label("Restore"),
+            ])]),
+        Some(backup) => CreateInteractionResponseMessage::new()
+            .ephemeral(true)
+            .content(MessageBuilder::default().push(format!("Backup {id} can't be restored: ")).push_safe(backup.status()).build()),
+        None => CreateInteractionResponseMessage::new()
+            .ephemeral(true)
+            .content(format!("There is no backup with this ID. Use /backup list to see recent backups.")),
+    })
+}
+
+/// Returns the backup to restore if the given custom ID belongs to a restore confirmation button.
+pub(crate) fn parse_custom_id(custom_id: &str) -> Option<i32> {
+    custom_id.strip_prefix(RESTORE_CUSTOM_ID_PREFIX)?.parse().ok()
+}
+
+/// Handles a click on a restore confirmation button, replacing the confirmation prompt with the progress of the restore.
+pub(crate) async fn handle_restore_button(ctx: &Context, interaction: &ComponentInteraction, id: i32) -> Result<(), Error> {
+    let guild = crate::discord::main_guild(ctx).await;
+    if !interaction.member.as_ref().is_some_and(|member| member.guild_id == guild.id && member.roles.contains(&guild.admin_role)) {
+        interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+            .ephemeral(true)
+            .content("Only admins can restore backups.")
+        )).await?;
+        return Ok(())
+    }
+    let pool = ctx.data.read().await.get::<DbPool>().expect("missing database connection").clone();
+    let Some(backup) = Backup::from_id(&pool, id).await?.filter(Backup::is_restorable) else {
+        interaction.create_response(ctx, CreateInteractionResponse::UpdateMessage(CreateInteractionResponseMessage::new()
+            .content(format!("Backup {id} can no longer be restored."))
+            .components(Vec::default())
+        )).await?;
+        return Ok(())
+    };
+    interaction.create_response(ctx, CreateInteractionResponse::UpdateMessage(CreateInteractionResponseMessage::new()
+        .content(MessageBuilder::default().push(format!("Restoring backup {id} of ")).push_safe(&backup.world).push(" world…").build())
+        .components(Vec::default())
+    )).await?;
+    let reply = match restore(ctx, &backup, Some(interaction.user.id)).await {
+        Ok(safety_backup) => {
+            guild.dev_channel.say(ctx, MessageBuilder::default()
+                .mention(&interaction.user)
+                .push(format!(" restored backup {id} of "))
+                .push_safe(&backup.world)
+                .push(format!(" world. The previous state was saved as backup {safety_backup}."))
+                .build()
+            ).await?;
+            format!("Backup {id} restored. The previous state was saved as backup {safety_backup}.")
+        }
+        Err(e) => MessageBuilder::default().push("Restore error: ").push_safe(e.to_string()).push(" (").push_mono_safe(format!("{e:?}")).push(")").build(),
+    };
+    // restores can take longer than the interaction token is valid, so the result is posted as a new message
+    interaction.channel_id.say(ctx, MessageBuilder::default().mention(&interaction.user).push(" ").push(reply).build()).await?;
+    Ok(())
+}
+
+#[rocket::get("/admin/backups")]
+pub(crate) async fn get(db_pool: &State<PgPool>, config: &State<Config>, me: Option<User>, uri: Origin<'_>) -> Result<(Status, RawHtml<String>), Error> {
+    if !me.as_ref().is_some_and(|me| me.is_discord_admin(config)) {
+        return Ok((Status::Forbidden, page(&me, &uri, PageStyle::default(), "Forbidden — Wurstmineberg", Tab::None, html! {
+            h1 : "Error 403: Forbidden";
+            p : "The list of backups is only available to admins.";
+        })))
+    }
+    let backups = Backup::recent(db_pool, None, 500).await?;
+    Ok((Status::Ok, page(&me, &uri, PageStyle::default(), "Backups — Wurstmineberg", Tab::None, html! {
+        h1 : "Backups";
+        p {
+            @if let Some(retention_days) = config.wurstminebot.backup_retention_days {
+                : "Backups are kept for ";
+                : retention_days;
+                : " days, except for the latest backup of each world.";
+            } else {
+                : "Backups are kept forever.";
+            }
+            : " Use ";
+            code : "/backup restore";
+            : " on Discord to restore one.";
+        }
+        table(class = "table table-responsive") {
+            thead {
+                tr {
+                    th : "ID";
+                    th : "World";
+                    th : "Started";
+                    th : "Reason";
+                    th : "By";
+                    th : "Status";
+                }
+            }
+            tbody {
+                @for backup in backups {
+                    tr {
+                        td : backup.id;
+                        td : &backup.world;
+                        td : format_datetime(backup.started_at, DateTimeFormat { long: false, running_text: false });
+                        td : &backup.reason;
+                        td {
+                            @if let Some(PgSnowflake(created_by)) = backup.created_by {
+                                @if let Some(user) = User::from_discord(&**db_pool, created_by).await? {
+                                    : user;
+                                } else {
+                                    : "unknown";
+                                }
+                            } else {
+                                : "wurstminebot";
+                            }
+                        }
+                        td : backup.status();
+                    }
+                }
+            }
+        }
+    })))
+}
@@ -1,16 +1,24 @@
 use {
-    std::borrow::Cow,
+    std::{
+        borrow::Cow,
+        collections::HashSet,
+    },
     chrono::{
         Duration,
         prelude::*,
     },
-    serde::Deserialize,
+    serde::{
+        Deserialize,
+        Serialize,
+    },
     serenity::all::{
         Colour,
         Context,
+        CreateAllowedMentions,
         CreateEmbed,
         CreateMessage,
         MessageBuilder,
+        UserId,
     },
     serenity_utils::RwFuture,
     sqlx::{
@@ -21,8 +29,8 @@ use {
     crate::{
         discord::{
             self,
-            GENERAL,
             MessageBuilderExt as _,
+            PgSnowflake,
         },
         Error,
         lang::join_opt,
@@ -33,7 +41,10 @@ use {
     },
 };
 
-#[derive(Clone, Deserialize)]
+/// How often the calendar is checked for events starting soon, so that events added while the bot is running are also announced.
+const NOTIFICATION_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_mins(1);
+
+#[derive(Clone, Deserialize, Serialize)]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub(crate) enum EventKind {
     Minigame {
@@ -69,6 +80,35 @@ pub(crate) struct Event {
 }
 
 impl Event {
+    pub(crate) async fn from_id(pool: &PgPool, id: i32) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as!(Self, r#"SELECT id, start_time AS "start_time: DateTime<Utc>", end_time AS "end_time: DateTime<Utc>", kind as "kind: Json<EventKind>" FROM calendar WHERE id = $1"#, id).fetch_optional(pool).await
+    }
+
+    /// Events that haven't ended yet, ordered by start time.
+    pub(crate) async fn upcoming(pool: &PgPool) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as!(Self, r#"SELECT id, start_time AS "start_time: DateTime<Utc>", end_time AS "end_time: DateTime<Utc>", kind as "kind: Json<EventKind>" FROM calendar WHERE end_time > $1 ORDER BY start_time"#, Utc::now()).fetch_all(pool).await
+    }
+
+    pub(crate) async fn create(pool: &PgPool, start_time: DateTime<Utc>, end_time: DateTime<Utc>, kind: EventKind) -> sqlx::Result<Self> {
+        let id = sqlx::query_scalar!("INSERT INTO calendar (start_time, end_time, kind) VALUES ($1, $2, $3) RETURNING id", start_time, end_time, Json(&kind) as _).fetch_one(pool).await?;
+        Ok(Self { id, start_time, end_time, kind: Json(kind) })
+    }
+
+    /// The Discord users who said they would attend this event.
+    pub(crate) async fn attendees(&self, pool: &PgPool) -> sqlx::Result<Vec<UserId>> {
+        Ok(sqlx::query_scalar!(r#"SELECT snowflake AS "snowflake: PgSnowflake<UserId>" FROM calendar_rsvps WHERE event = $1"#, self.id).fetch_all(pool).await?.into_iter().map(|PgSnowflake(user_id)| user_id).collect())
+    }
+
+    /// Records whether the given Discord user will attend this event. Returns `false` if this didn't change anything.
+    pub(crate) async fn rsvp(&self, pool: &PgPool, user_id: UserId, attending: bool) -> sqlx::Result<bool> {
+        let result = if attending {
+            sqlx::query!("INSERT INTO calendar_rsvps (event, snowflake) VALUES ($1, $2) ON CONFLICT DO NOTHING", self.id, PgSnowflake(user_id) as _).execute(pool).await?
+        } else {
+            sqlx::query!("DELETE FROM calendar_rsvps WHERE event = $1 AND snowflake = $2", self.id, PgSnowflake(user_id) as _).execute(pool).await?
+        };
+        Ok(result.rows_affected() > 0)
+    }
+
     pub(crate) async fn title(&self, pool: &PgPool) -> sqlx::Result<String> {
         Ok(match self.kind.0 {
             EventKind::Minigame { ref minigame } => format!("Minigame Night: {}", minigame),
@@ -165,41 +205,48 @@ impl Event {
             EventKind::Other { location: None, .. } => None,
         }
     }
+
+    pub(crate) async fn discord_embed(&self, pool: &PgPool) -> sqlx::Result<CreateEmbed> {
+        let mut title = MessageBuilder::default();
+        self.title_discord(pool, &mut title).await?;
+        let mut e = CreateEmbed::new()
+            .colour(Colour(8794372))
+            .title(title.build());
+        if let Some(loc) = self.discord_location() {
+            e = e.description(loc);
+        }
+        Ok(e.field("starts", format!("<t:{}:F>", self.start_time.timestamp()), false)
+            .field("ends", format!("<t:{}:F>", self.end_time.timestamp()), false))
+    }
 }
 
 pub(crate) async fn notifications(ctx_fut: RwFuture<Context>) -> Result<(), Error> {
     let ctx = ctx_fut.read().await;
-    let mut unnotified = {
-        let data = (*ctx).data.read().await;
-        let pool = data.get::<discord::DbPool>().expect("missing database connection");
+    let mut notified = HashSet::new();
+    loop {
         let now = Utc::now();
-        sqlx::query_as!(Event, r#"SELECT id, start_time AS "start_time: DateTime<Utc>", end_time AS "end_time: DateTime<Utc>", kind as "kind: Json<EventKind>" FROM calendar WHERE start_time > $1 ORDER BY start_time"#, (now + Duration::minutes(30)) as _).fetch_all(pool).await?
-    };
-    while !unnotified.is_empty() {
-        let event = unnotified.remove(0);
-        if let Ok(duration) = (event.start_time - Duration::minutes(30) - Utc::now()).to_std() {
-            sleep(duration).await;
-        }
-        let title = {
-            let mut builder = MessageBuilder::default();
+        let (pool, general) = {
             let data = (*ctx).data.read().await;
-            let pool = data.get::<discord::DbPool>().expect("missing database connection");
-            event.title_discord(pool, &mut builder).await?;
-            builder.build()
+            (data.get::<discord::DbPool>().expect("missing database connection").clone(), data.get::<crate::config::Config>().expect("missing config").wurstminebot.main_guild.general_channel)
         };
-        GENERAL.send_message(&*ctx, CreateMessage::new()
-            .content(format!("event starting <t:{}:R>", event.start_time.timestamp()))
-            .add_embed({
-                let mut e = CreateEmbed::new()
-                    .colour(Colour(8794372))
-                    .title(title);
-                if let Some(loc) = event.discord_location() {
-                    e = e.description(loc);
-                }
-                e.field("starts", format!("<t:{}:F>", event.start_time.timestamp()), false)
-                    .field("ends", format!("<t:{}:F>", event.end_time.timestamp()), false)
-            })
-        ).await?;
+        let starting_soon = sqlx::query_as!(Event, r#"SELECT id, start_time AS "start_time: DateTime<Utc>", end_time AS "end_time: DateTime<Utc>", kind as "kind: Json<EventKind>" FROM calendar WHERE start_time > $1 AND start_time <= $2 ORDER BY start_time"#, now as _, (now + Duration::minutes(30)) as _).fetch_all(&pool).await?;
+        // events that have started won't be selected again, so they can be forgotten
+        notified.retain(|id| starting_soon.iter().any(|event| event.id == *id));
+        for event in starting_soon {
+            if !notified.insert(event.id) { continue }
+            let attendees = event.attendees(&pool).await?;
+            let mut content = MessageBuilder::default();
+            content.push(format!("event starting <t:{}:R>", event.start_time.timestamp()));
+            for &attendee in &attendees {
+                content.push(' ');
+                content.mention(&attendee);
+            }
+            general.send_message(&*ctx, CreateMessage::new()
+                .content(content.build())
+                .allowed_mentions(CreateAllowedMentions::new().users(attendees)) // only ping people who RSVP'd
+                .add_embed(event.discord_embed(&pool).await?)
+            ).await?;
+        }
+        sleep(NOTIFICATION_CHECK_INTERVAL).await;
     }
-    Ok(())
 }
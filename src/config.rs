@@ -1,9 +1,15 @@
 use {
-    std::collections::{
-        HashMap,
-        HashSet,
+    std::{
+        collections::{
+            HashMap,
+            HashSet,
+        },
+        iter,
+    },
+    serde::{
+        Deserialize,
+        de::IgnoredAny,
     },
-    serde::Deserialize,
     serenity::{
         model::prelude::*,
         prelude::TypeMapKey,
@@ -24,6 +30,8 @@ use {
 pub(crate) enum Error {
     #[cfg(windows)] #[error(transparent)] Json(#[from] serde_json::Error),
     #[error(transparent)] Wheel(#[from] wheel::Error),
+    #[error("the wurstminebot config entry {0} has been replaced by the worldChannels/selfAssignableRoles entries of mainGuild and otherGuilds")]
+    Unmigrated(&'static str),
 }
 
 #[derive(Clone, Deserialize)]
@@ -37,10 +45,14 @@ pub(crate) struct Config {
 
 impl Config {
     pub(crate) async fn load() -> Result<Self, Error> {
-        #[cfg(unix)] { Ok(fs::read_json(Path::new(BASE_PATH).join("config.json")).await?) }
-        #[cfg(windows)] { // allow testing without having rust-analyzer slow down the server
-            Ok(serde_json::from_slice(&Command::new("ssh").arg("wurstmineberg.de").arg("cat").arg("/opt/wurstmineberg/config.json").output().await.at_command("ssh")?.stdout)?)
-        }
+        #[cfg(unix)] let config: Self = fs::read_json(Path::new(BASE_PATH).join("config.json")).await?;
+        #[cfg(windows)] let config: Self = { // allow testing without having rust-analyzer slow down the server
+            serde_json::from_slice(&Command::new("ssh").arg("wurstmineberg.de").arg("cat").arg("/opt/wurstmineberg/config.json").output().await.at_command("ssh")?.stdout)?
+        };
+        // refuse to start with a config from before multi-guild support rather than silently losing these settings
+        if config.wurstminebot.legacy_self_assignable_roles.is_some() { return Err(Error::Unmigrated("selfAssignableRoles")) }
+        if config.wurstminebot.legacy_world_channels.is_some() { return Err(Error::Unmigrated("worldChannels")) }
+        Ok(config)
     }
 }
 
@@ -79,14 +91,108 @@ pub(crate) struct Web {
 #[derive(Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct Wurstminebot {
+    /// Backups older than this many days are deleted, except for the latest backup of each world. Backups are kept forever if this is not set.
+    #[serde(default)]
+    pub(crate) backup_retention_days: Option<u16>,
     pub(crate) bot_token: String,
     #[serde(rename = "clientID")]
     pub(crate) client_id: ApplicationId,
     pub(crate) client_secret: String,
+    #[serde(default, rename = "selfAssignableRoles")]
+    legacy_self_assignable_roles: Option<IgnoredAny>,
+    #[serde(default, rename = "worldChannels")]
+    legacy_world_channels: Option<IgnoredAny>,
+    #[serde(default)]
+    pub(crate) log_routing: HashMap<systemd_minecraft::World, HashMap<LogEvent, LogRoute>>,
+    /// The Wurstmineberg guild, whose members are synced with the people database.
+    pub(crate) main_guild: Guild,
+    /// Additional guilds the bot is in, e.g. for testing.
+    #[serde(default)]
+    pub(crate) other_guilds: Vec<Guild>,
+    #[serde(default)]
+    pub(crate) world_channel_topics: HashMap<systemd_minecraft::World, String>,
+}
+
+impl Wurstminebot {
+    pub(crate) fn guilds(&self) -> impl Iterator<Item = &Guild> {
+        iter::once(&self.main_guild).chain(&self.other_guilds)
+    }
+
+    /// The configuration for the given guild, or `None` if the bot isn't configured to be in that guild.
+    pub(crate) fn guild(&self, guild_id: GuildId) -> Option<&Guild> {
+        self.guilds().find(|guild| guild.id == guild_id)
+    }
+
+    /// The Minecraft world associated with the given channel in any guild.
+    pub(crate) fn world_for_channel(&self, channel_id: ChannelId) -> Option<&systemd_minecraft::World> {
+        self.guilds().find_map(|guild| guild.world_channels.iter().find(|(_, chan_id)| **chan_id == channel_id).map(|(world, _)| world))
+    }
+
+    /// The channels to which the given log event from the given world should be sent.
+    ///
+    /// By default, this is only the world's channel in each guild, but this can be changed using the `logRouting` config entry.
+    pub(crate) fn log_channels(&self, world: &systemd_minecraft::World, event: LogEvent) -> Vec<ChannelId> {
+        let route = self.log_routing.get(world).and_then(|routes| routes.get(&event));
+        let mut channels = Vec::default();
+        if route.is_none_or(|route| route.enabled) {
+            channels.extend(self.guilds().filter_map(|guild| guild.world_channels.get(world).copied()));
+        }
+        if let Some(route) = route {
+            for &channel in &route.extra_channels {
+                if !channels.contains(&channel) {
+                    channels.push(channel);
+                }
+            }
+        }
+        channels
+    }
+}
+
+/// Configuration for one of the guilds wurstminebot is in.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Guild {
+    pub(crate) id: GuildId,
+    pub(crate) admin_role: RoleId,
+    /// Where bot error reports and admin notifications are posted.
+    pub(crate) dev_channel: ChannelId,
+    /// Where announcements like invites and events are posted.
+    pub(crate) general_channel: ChannelId,
+    /// Where moderation events like deleted messages and bans are logged. Moderation logging is disabled for this guild if this is not set.
+    #[serde(default)]
+    pub(crate) audit_channel: Option<ChannelId>,
     #[serde(default)]
     pub(crate) self_assignable_roles: HashSet<RoleId>,
     #[serde(default)]
     pub(crate) world_channels: HashMap<systemd_minecraft::World, ChannelId>,
+}
+
+/// The types of events from the Minecraft server log which are relayed to Discord.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum LogEvent {
+    Chat,
+    AdvancementTask,
+    AdvancementGoal,
+    AdvancementChallenge,
+    Death,
+    /// Messages from `/say`, command blocks, the server console, or RCON.
+    Say,
+    ServerStart,
+    ServerStop,
+    ServerCrash,
+    Lag,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LogRoute {
+    /// Whether this event is sent to the world's channel.
+    #[serde(default = "make_true")]
+    pub(crate) enabled: bool,
+    /// Channels to which this event is sent in addition to the world's channel.
     #[serde(default)]
-    pub(crate) world_channel_topics: HashMap<systemd_minecraft::World, String>,
+    pub(crate) extra_channels: Vec<ChannelId>,
 }
+
+fn make_true() -> bool { true }
@@ -1,28 +1,48 @@
 use {
     std::{
+        collections::{
+            HashMap,
+            VecDeque,
+        },
         num::NonZero,
         path::Path,
         pin::Pin,
         time::Duration,
     },
+    chrono::prelude::*,
+    chrono_tz::{
+        Etc,
+        Tz,
+    },
     discord_message_parser::{
         MessagePart,
         TimestampStyle,
         serenity::MessageExt as _,
     },
     itertools::Itertools as _,
+    lazy_regex::{
+        regex,
+        regex_captures,
+    },
     minecraft::chat::Chat,
     rand::{
         prelude::*,
         rng,
     },
+    rocket::uri,
     serde_json::json,
     serenity::{
         all::{
+            Colour,
+            CreateAllowedMentions,
+            CreateAutocompleteResponse,
             CreateCommand,
             CreateCommandOption,
+            CreateEmbed,
             CreateInteractionResponse,
             CreateInteractionResponseMessage,
+            CreateMessage,
+            EditInteractionResponse,
             MessageBuilder,
         },
         model::prelude::*,
@@ -42,35 +62,42 @@ use {
         PgPool,
         postgres::PgConnectOptions,
     },
-    tokio::{
-        process::Command,
-        time::{
-            Instant,
-            sleep,
-        },
-    },
-    wheel::{
-        fs,
-        traits::AsyncCommandOutputExt as _,
+    tokio::time::{
+        Instant,
+        sleep,
     },
+    uuid::Uuid,
+    wheel::fs,
     crate::{
         BASE_PATH,
         cal,
-        config::Config,
-        user::User,
+        config::{
+            self,
+            Config,
+        },
+        time::DateWithOptionalTime,
+        user::{
+            self,
+            User,
+        },
     },
 };
 #[cfg(not(target_os = "linux"))] use crate::systemd_minecraft;
 
-pub(crate) const GENERAL: ChannelId = ChannelId::new(88318761228054528);
-const DEV: ChannelId = ChannelId::new(506905544901001228);
-
-pub(crate) const GUILD: GuildId = GuildId::new(88318761228054528);
-
-pub(crate) const ADMIN: RoleId = RoleId::new(88329417788502016);
+/// How often backups older than the configured retention period are deleted.
+const BACKUP_PRUNE_INTERVAL: Duration = Duration::from_hours(24);
+/// How many recently relayed Discord messages are remembered, so that their deletion can be announced in Minecraft.
+const RELAYED_MESSAGES_CAPACITY: usize = 1000;
+/// How often invites are checked for the end of their veto period.
+const INVITE_CHECK_INTERVAL: Duration = Duration::from_mins(5);
+/// How often the Minecraft whitelists are checked against the people database.
+const WHITELIST_SYNC_INTERVAL: Duration = Duration::from_hours(1);
 
 #[derive(Debug, thiserror::Error)]
 enum Error {
+    #[error(transparent)] Minecraft(#[from] systemd_minecraft::Error),
+    #[error(transparent)] Serenity(#[from] serenity::Error),
+    #[error(transparent)] Sql(#[from] sqlx::Error),
     #[error("encountered user without join date")]
     MissingJoinDate,
 }
@@ -118,11 +145,28 @@ where i64: sqlx::Type<DB> {
     }
 }
 
-enum UserListExporter {}
+pub(crate) enum UserListExporter {}
 
 impl serenity_utils::handler::user_list::ExporterMethods for UserListExporter {
     fn upsert<'a>(ctx: &'a Context, member: &'a Member) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error + Send + Sync>>> + Send + 'a>> {
         Box::pin(async move {
+            if member.guild_id != main_guild(ctx).await.id { return Ok(()) } // only members of the main guild are synced with the people database
+            let person = {
+                let data = ctx.data.read().await;
+                let pool = data.get::<DbPool>().expect("missing database connection");
+                User::from_discord(pool, member.user.id).await?
+            };
+            if let Some(person) = person { // without a member cache, the previous state is only known for people in the database
+                let role_changes = match person.discorddata {
+                    Some(ref discorddata) => crate::member::RoleChanges::new(ctx, member, &discorddata.roles).await?,
+                    None => crate::member::RoleChanges::default(),
+                };
+                // the moderation log is best-effort and shouldn't keep the people database from being updated
+                if let Err(e) = crate::audit::record_member_update(ctx, member, &person, &role_changes).await {
+                    eprintln!("failed to record member update in moderation log: {e} ({e:?})");
+                }
+                crate::member::record_changes(ctx, member, &person, &role_changes).await?;
+            }
             let data = ctx.data.read().await;
             let pool = data.get::<DbPool>().expect("missing database connection");
             //TODO update display name in data column
@@ -148,8 +192,13 @@ impl serenity_utils::handler::user_list::ExporterMethods for UserListExporter {
         })
     }
 
-    fn remove<'a>(ctx: &'a Context, user_id: UserId, _: GuildId) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error + Send + Sync>>> + Send + 'a>> {
+    fn remove<'a>(ctx: &'a Context, user_id: UserId, guild_id: GuildId) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error + Send + Sync>>> + Send + 'a>> {
         Box::pin(async move {
+            if let Err(e) = crate::audit::record_member_removal(ctx, guild_id, user_id).await {
+                eprintln!("failed to record member removal in moderation log: {e} ({e:?})");
+            }
+            if guild_id != main_guild(ctx).await.id { return Ok(()) } // only members of the main guild are synced with the people database
+            crate::member::record_leave(ctx, user_id).await?;
             let data = ctx.data.read().await;
             let pool = data.get::<DbPool>().expect("missing database connection");
             sqlx::query!("UPDATE people SET discorddata = NULL WHERE snowflake = $1", i64::from(user_id))
@@ -162,8 +211,10 @@ impl serenity_utils::handler::user_list::ExporterMethods for UserListExporter {
 enum VoiceStateExporter {}
 
 impl serenity_utils::handler::voice_state::ExporterMethods for VoiceStateExporter {
-    fn dump_info<'a>(_: &'a Context, _: GuildId, VoiceStates(voice_states): &'a VoiceStates) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error + Send + Sync>>> + Send + 'a>> {
+    fn dump_info<'a>(ctx: &'a Context, guild_id: GuildId, voice_state: &'a VoiceStates) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error + Send + Sync>>> + Send + 'a>> {
         Box::pin(async move {
+            if guild_id != main_guild(ctx).await.id { return Ok(()) } // the website and in-game notifications only show the main guild's voice channels
+            let VoiceStates(voice_states) = voice_state;
             let buf = serde_json::to_vec_pretty(&json!({
                 "channels": voice_states.into_iter()
                     .map(|(channel_id, (channel_name, members))| json!({
@@ -180,19 +231,130 @@ impl serenity_utils::handler::voice_state::ExporterMethods for VoiceStateExporte
                     .collect_vec()
             }))?;
             fs::write(Path::new(BASE_PATH).join("discord").join("voice-state.json"), buf).await?;
+            crate::voice::update(ctx, crate::voice::channels(voice_state)).await?;
             Ok(())
         })
     }
 }
 
-fn discord_to_minecraft<'a>(ctx: &'a Context, msg: &'a Message, chat: &'a mut Chat, part: MessagePart<'a>) -> Pin<Box<dyn Future<Output = serenity::Result<()>> + Send + 'a>> {
+/// Readable replacements for common emoji, which Minecraft's font can't display.
+fn emoji_shortcode(emoji: &str) -> Option<&'static str> {
+    Some(match emoji.trim_end_matches('\u{fe0f}') { // ignore variation selector
+        "😀" => "grinning",
+        "😃" => "smiley",
+        "😄" => "smile",
+        "😁" => "grin",
+        "😆" => "laughing",
+        "😅" => "sweat_smile",
+        "😂" => "joy",
+        "🤣" => "rofl",
+        "🙂" => "slight_smile",
+        "🙃" => "upside_down",
+        "😉" => "wink",
+        "😊" => "blush",
+        "😍" => "heart_eyes",
+        "😘" => "kissing_heart",
+        "😋" => "yum",
+        "😛" => "stuck_out_tongue",
+        "😜" => "stuck_out_tongue_winking_eye",
+        "🤔" => "thinking",
+        "🤨" => "raised_eyebrow",
+        "😐" => "neutral_face",
+        "😑" => "expressionless",
+        "😶" => "no_mouth",
+        "🙄" => "rolling_eyes",
+        "😏" => "smirk",
+        "😬" => "grimacing",
+        "😌" => "relieved",
+        "😴" => "sleeping",
+        "😎" => "sunglasses",
+        "🤓" => "nerd",
+        "😕" => "confused",
+        "😟" => "worried",
+        "😮" => "open_mouth",
+        "😲" => "astonished",
+        "😳" => "flushed",
+        "🥺" => "pleading_face",
+        "😢" => "cry",
+        "😭" => "sob",
+        "😱" => "scream",
+        "😤" => "triumph",
+        "😡" => "rage",
+        "😠" => "angry",
+        "💀" => "skull",
+        "👍" => "thumbsup",
+        "👎" => "thumbsdown",
+        "👌" => "ok_hand",
+        "👋" => "wave",
+        "👏" => "clap",
+        "🙏" => "pray",
+        "💪" => "muscle",
+        "👀" => "eyes",
+        "❤" => "heart",
+        "💔" => "broken_heart",
+        "🔥" => "fire",
+        "✨" => "sparkles",
+        "🎉" => "tada",
+        "💯" => "100",
+        "✅" => "white_check_mark",
+        "❌" => "x",
+        "❓" => "question",
+        "❗" => "exclamation",
+        "⚠" => "warning",
+        "🐷" => "pig",
+        "🐔" => "chicken",
+        "🐄" => "cow",
+        "🐑" => "sheep",
+        "🐝" => "bee",
+        "🐈" => "cat2",
+        "🐕" => "dog2",
+        "🌭" => "hotdog",
+        "🍰" => "cake",
+        "🍪" => "cookie",
+        "💎" => "gem",
+        "⛏" => "pick",
+        "🗡" => "dagger",
+        "🏹" => "bow_and_arrow",
+        "🛡" => "shield",
+        _ => return None,
+    })
+}
+
+fn contains_timestamp(part: &MessagePart<'_>) -> bool {
+    match part {
+        MessagePart::Nested(parts) => parts.iter().any(contains_timestamp),
+        MessagePart::Timestamp { .. } => true,
+        _ => false,
+    }
+}
+
+/// The names and UUIDs of the players currently online in the given world, or `None` if the response to `list uuids` couldn't be parsed.
+pub(crate) async fn online_players(world: &systemd_minecraft::World) -> Result<Option<Vec<(String, Uuid)>>, systemd_minecraft::Error> {
+    let response = world.command("list uuids").await?;
+    let Some((_, players)) = regex_captures!("^There are [0-9]+ of a max of [0-9]+ players online: ?(.*)$", response.trim()) else { return Ok(None) };
+    Ok(players.split(", ")
+        .filter(|player| !player.is_empty())
+        .map(|player| regex_captures!("^(.+) \\(([0-9a-f-]+)\\)$", player).and_then(|(_, name, uuid)| Some((name.to_owned(), uuid.parse().ok()?))))
+        .collect())
+}
+
+/// Renders a Discord message for Minecraft chat, with timestamps shown in the given time zone.
+fn discord_to_minecraft<'a>(ctx: &'a Context, msg: &'a Message, chat: &'a mut Chat, part: MessagePart<'a>, timezone: Tz) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send + 'a>> {
     Box::pin(async move {
         match part {
             MessagePart::Empty => {}
             MessagePart::Nested(parts) => for part in parts {
-                discord_to_minecraft(ctx, msg, chat, part).await?;
+                discord_to_minecraft(ctx, msg, chat, part, timezone).await?;
             },
-            MessagePart::PlainText(text) => { chat.add_extra(text); }
+            MessagePart::PlainText(text) => {
+                let mut last_end = 0;
+                for url in regex!("https?://[^\\s<>]*[^\\s<>.,:;!?)'\"]").find_iter(&text) {
+                    chat.add_extra(&text[last_end..url.start()]);
+                    chat.add_extra(link_component(url.as_str().to_owned(), Some(url.as_str().to_owned()), None));
+                    last_end = url.end();
+                }
+                chat.add_extra(&text[last_end..]);
+            }
             MessagePart::UserMention { user, nickname_mention: _ } => {
                 let (tag, nickname) = if let Some(guild_id) = msg.guild_id {
                     let member = guild_id.member(ctx, user).await?;
@@ -207,19 +369,27 @@ fn discord_to_minecraft<'a>(ctx: &'a Context, msg: &'a Message, chat: &'a mut Ch
                         (tag.unwrap_or_else(|| user.tag()), nickname.unwrap_or(user.name))
                     }
                 };
+                // clicking the mention puts a mention of the same person into the chat input, using a name that the Minecraft → Discord relay can resolve
+                let mention_name = {
+                    let data = ctx.data.read().await;
+                    let pool = data.get::<DbPool>().expect("missing database connection");
+                    User::from_discord(pool, user).await?.and_then(|user| user.minecraft_nick().map(str::to_owned).or_else(|| user.id.wmbid().map(str::to_owned)))
+                };
                 let mut extra = Chat::from(format!("@{}", nickname));
-                //TODO add mention to chat input on click? (blue + underline)
+                if let Some(mention_name) = mention_name {
+                    clickable(&mut extra);
+                    extra.on_click(minecraft::chat::ClickEvent::SuggestCommand(format!("@{mention_name} ")));
+                }
                 extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(tag))));
                 chat.add_extra(extra);
             }
             MessagePart::ChannelMention(channel) => {
-                let extra = Chat::from(match channel.to_channel(ctx).await? {
-                    Channel::Guild(channel) => format!("#{}", channel.name),
-                    Channel::Private(dm) => dm.name(),
+                let (name, url) = match channel.to_channel(ctx).await? {
+                    Channel::Guild(channel) => (format!("#{}", channel.name), format!("https://discord.com/channels/{}/{}", channel.guild_id, channel.id)),
+                    Channel::Private(dm) => (dm.name(), format!("https://discord.com/channels/@me/{}", dm.id)),
                     _ => panic!("unexpected channel type"),
-                });
-                //TODO open channel in browser on click? (blue + underline)
-                chat.add_extra(extra);
+                };
+                chat.add_extra(link_component(name, Some(url), None));
             }
             MessagePart::RoleMention(role) => {
                 let mut extra = Chat::from(format!("<@&{}>", role));
@@ -231,18 +401,42 @@ fn discord_to_minecraft<'a>(ctx: &'a Context, msg: &'a Message, chat: &'a mut Ch
                 }
                 chat.add_extra(extra);
             }
-            MessagePart::UnicodeEmoji(text) => { chat.add_extra(text); } //TODO special handling for emoji where possible
+            MessagePart::UnicodeEmoji(text) => if let Some(shortcode) = emoji_shortcode(&text) {
+                // Minecraft's font is missing most emoji, so show a readable shortcode and keep the emoji itself in the hover text
+                let mut extra = Chat::from(format!(":{shortcode}:"));
+                extra.color(minecraft::chat::Color::Yellow);
+                extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(&*text))));
+                chat.add_extra(extra);
+            } else {
+                chat.add_extra(text);
+            },
             MessagePart::CustomEmoji(emoji) => {
-                chat.add_extra(format!(":{}:", emoji.name));
+                let mut extra = Chat::from(format!(":{}:", emoji.name));
+                extra.color(minecraft::chat::Color::Yellow);
+                chat.add_extra(extra);
             }
             MessagePart::Timestamp { timestamp, style } => {
-                let mut extra = Chat::from(style.unwrap_or_default().fmt(timestamp)); //TODO convert to user timezone? (Would require replacing @a with individual commands)
-                extra.underlined();
-                if let Some(TimestampStyle::RelativeTime) = style {
-                    extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(timestamp.format("%Y-%m-%d %H:%M:%S UTC").to_string())))); //TODO show user timezone if converted
+                let local = timestamp.with_timezone(&timezone);
+                let mut extra = if let Some(TimestampStyle::RelativeTime) = style {
+                    let mut extra = Chat::from(TimestampStyle::RelativeTime.fmt(timestamp));
+                    extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(local.format("%Y-%m-%d %H:%M:%S %Z").to_string()))));
+                    extra
                 } else {
-                    extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from("UTC")))); //TODO show user timzeone if converted
-                }
+                    // same formats as Discord's English (UK) locale, since Minecraft doesn't tell us the player's locale
+                    let format = match style.unwrap_or(TimestampStyle::ShortDateTime) {
+                        TimestampStyle::ShortTime => "%H:%M",
+                        TimestampStyle::LongTime => "%H:%M:%S",
+                        TimestampStyle::ShortDate => "%d/%m/%Y",
+                        TimestampStyle::LongDate => "%-d %B %Y",
+                        TimestampStyle::ShortDateTime => "%-d %B %Y %H:%M",
+                        TimestampStyle::LongDateTime => "%A, %-d %B %Y %H:%M",
+                        TimestampStyle::RelativeTime => unreachable!("handled above"),
+                    };
+                    let mut extra = Chat::from(local.format(format).to_string());
+                    extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(format!("{} ({})", timezone.name(), local.format("%Z"))))));
+                    extra
+                };
+                extra.underlined();
                 chat.add_extra(extra);
             }
         }
@@ -250,6 +444,146 @@ fn discord_to_minecraft<'a>(ctx: &'a Context, msg: &'a Message, chat: &'a mut Ch
     })
 }
 
+/// The Minecraft world whose chat is bridged to the given channel, if any.
+async fn world_for_channel(ctx: &Context, channel_id: ChannelId) -> Option<systemd_minecraft::World> {
+    ctx.data.read().await.get::<Config>().expect("missing config").wurstminebot.world_for_channel(channel_id).cloned()
+}
+
+/// The configuration of the Wurstmineberg guild, whose members are synced with the people database.
+pub(crate) async fn main_guild(ctx: &Context) -> config::Guild {
+    ctx.data.read().await.get::<Config>().expect("missing config").wurstminebot.main_guild.clone()
+}
+
+/// The configuration of the given guild, or `None` if the bot isn't configured to be in that guild.
+async fn guild_config(ctx: &Context, guild_id: Option<GuildId>) -> Option<config::Guild> {
+    ctx.data.read().await.get::<Config>().expect("missing config").wurstminebot.guild(guild_id?).cloned()
+}
+
+/// Whether the given channel can be read by everyone in its guild, judging by the permission overwrites of the channel and its category.
+async fn is_public(ctx: &Context, channel: &GuildChannel) -> serenity::Result<bool> {
+    if channel.kind == ChannelType::PrivateThread { return Ok(false) }
+    let everyone = PermissionOverwriteType::Role(channel.guild_id.everyone_role());
+    let hides = |channel: &GuildChannel| channel.permission_overwrites.iter().any(|overwrite| overwrite.kind == everyone && overwrite.deny.contains(Permissions::VIEW_CHANNEL));
+    if hides(channel) { return Ok(false) }
+    if let Some(parent_id) = channel.parent_id {
+        if let Channel::Guild(parent) = parent_id.to_channel(ctx).await? {
+            // threads inherit from their parent channel, and channels synced with their category don't have their own overwrites
+            if hides(&parent) { return Ok(false) }
+        }
+    }
+    Ok(true)
+}
+
+/// Styles a chat component to show that it can be clicked.
+fn clickable(extra: &mut Chat) {
+    extra.color(minecraft::chat::Color::Blue);
+    extra.underlined();
+}
+
+/// A chat component for a link, attachment, sticker, or embed, which opens the URL on click if there is one.
+fn link_component(label: String, url: Option<String>, hover: Option<String>) -> Chat {
+    let mut extra = Chat::from(label);
+    if let Some(ref url) = url {
+        clickable(&mut extra);
+        extra.on_click(minecraft::chat::ClickEvent::OpenUrl(url.clone()));
+    } else {
+        extra.color(minecraft::chat::Color::Blue);
+    }
+    if let Some(hover) = hover.or(url) {
+        extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(hover))));
+    }
+    extra
+}
+
+/// Relays a Discord message to the chat of the given Minecraft world, if it's running.
+async fn relay_to_minecraft(ctx: &Context, world: &systemd_minecraft::World, msg: &Message, edited: bool) -> Result<(), Error> {
+    if !world.is_running().await? { return Ok(()) }
+    // timestamps are shown in each player's own time zone, which requires sending the message to each player individually
+    let mut recipients = Vec::default();
+    if contains_timestamp(&msg.parse()) {
+        if let Some(players) = online_players(world).await? {
+            let data = ctx.data.read().await;
+            let pool = data.get::<DbPool>().expect("missing database connection");
+            for (name, uuid) in players {
+                let timezone = User::from_minecraft_uuid(pool, uuid).await?.and_then(|user| user.data.timezone).unwrap_or(Etc::UTC);
+                recipients.push((name, timezone));
+            }
+        }
+    }
+    if recipients.is_empty() {
+        recipients.push((format!("@a"), Etc::UTC));
+    }
+    let channel_name = if let Channel::Guild(chan) = msg.channel(ctx).await? { chan.name.clone() } else { format!("?") };
+    for (rcpt, timezone) in recipients {
+        let mut chat = Chat::from(format!("[Discord:#{channel_name}"));
+        chat.color(minecraft::chat::Color::Aqua);
+        if let Some(ref in_reply_to) = msg.referenced_message {
+            chat.add_extra(", replying to ");
+            chat.add_extra({
+                let mut extra = Chat::from(in_reply_to.member.as_ref().and_then(|member| member.nick.as_deref()).unwrap_or(&in_reply_to.author.name));
+                extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(in_reply_to.author.tag()))));
+                extra
+            });
+        }
+        chat.add_extra("] ");
+        chat.add_extra({
+            let mut extra = Chat::from(format!("<{}>", msg.member.as_ref().and_then(|member| member.nick.as_ref()).unwrap_or(&msg.author.name)));
+            extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(msg.author.tag()))));
+            extra
+        });
+        if edited {
+            chat.add_extra(" (edited)");
+        }
+        chat.add_extra(" ");
+        discord_to_minecraft(ctx, msg, &mut chat, msg.parse(), timezone).await?;
+        for attachment in &msg.attachments {
+            let kind = match attachment.content_type.as_deref().and_then(|content_type| content_type.split_once('/')) {
+                Some(("image", _)) => "image",
+                Some(("video", _)) => "video",
+                Some(("audio", _)) => "audio",
+                _ => "file",
+            };
+            chat.add_extra(" ");
+            chat.add_extra(link_component(format!("[{kind}: {}]", attachment.filename), Some(attachment.url.clone()), None));
+        }
+        for sticker in &msg.sticker_items {
+            chat.add_extra(" ");
+            chat.add_extra(link_component(format!("[sticker: {}]", sticker.name), sticker.image_url(), None));
+        }
+        for embed in &msg.embeds {
+            if embed.url.as_ref().is_some_and(|url| msg.content.contains(&**url)) { continue } // link preview, the link itself is already in the message
+            let title = embed.title.clone().or_else(|| embed.author.as_ref().map(|author| author.name.clone())).unwrap_or_else(|| format!("embed"));
+            chat.add_extra(" ");
+            chat.add_extra(link_component(format!("[embed: {title}]"), embed.url.clone(), embed.description.clone()));
+        }
+        match world.tellraw(&rcpt, &chat).await {
+            Ok(_) => {}
+            Err(systemd_minecraft::Error::Rcon(rcon::Error::CommandTooLong)) => {
+                let mut chat = Chat::from(format!("[Discord:#{channel_name}] long message from "));
+                chat.color(minecraft::chat::Color::Aqua);
+                chat.add_extra({
+                    let mut extra = Chat::from(msg.member.as_ref().and_then(|member| member.nick.as_deref()).unwrap_or(&msg.author.name));
+                    extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(msg.author.tag()))));
+                    extra
+                });
+                chat.add_extra(" ");
+                chat.add_extra(link_component(format!("[open]"), Some(msg.link()), None));
+                world.tellraw(&rcpt, &chat).await?;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    if !edited {
+        let mut data = ctx.data.write().await;
+        let relayed = data.get_mut::<RelayedMessages>().expect("missing relayed messages");
+        if relayed.len() >= RELAYED_MESSAGES_CAPACITY {
+            relayed.pop_front();
+        }
+        relayed.push_back(msg.id);
+    }
+    Ok(())
+}
+
 pub(crate) trait MessageBuilderExt {
     fn mention_user(&mut self, user: &User) -> &mut Self;
 }
@@ -270,89 +604,565 @@ impl TypeMapKey for DbPool {
     type Value = PgPool;
 }
 
+pub(crate) enum HttpClient {}
+
+impl TypeMapKey for HttpClient {
+    type Value = reqwest::Client;
+}
+
+/// The most recent Discord messages that were relayed to Minecraft chat, oldest first.
+enum RelayedMessages {}
+
+impl TypeMapKey for RelayedMessages {
+    type Value = VecDeque<MessageId>;
+}
+
 #[derive(Clone, Copy)]
+/// The IDs of wurstminebot's slash commands, which are registered separately in each guild.
 struct CommandIds {
-    //TODO `/event` any-admin command to add or edit calendar events
+    backup: CommandId,
+    event: CommandId,
     iam: CommandId,
     iamn: CommandId,
+    invite: CommandId,
+    link: CommandId,
     ping: CommandId,
+    role_menu: CommandId,
+    status: CommandId,
     update: CommandId,
     veto: CommandId,
+    whitelist: CommandId,
 }
 
 impl TypeMapKey for CommandIds {
-    type Value = Self;
+    type Value = HashMap<GuildId, Self>;
+}
+
+/// Slash command data that doesn't match what wurstminebot registered, e.g. because of a stale command registration.
+#[derive(Debug, thiserror::Error)]
+enum CommandParseError {
+    #[error("unknown slash command /{0}")]
+    UnknownCommand(String),
+    #[error("unknown or missing subcommand of /{0}")]
+    Subcommand(String),
+    #[error("/{0} used outside of a guild")]
+    NotInGuild(String),
+    #[error("missing required option {option:?} for /{command}")]
+    MissingOption {
+        command: String,
+        option: &'static str,
+    },
+    #[error("wrong type for option {option:?} of /{command}")]
+    OptionType {
+        command: String,
+        option: &'static str,
+    },
+    #[error("value for option {option:?} of /{command} is out of range")]
+    OutOfRange {
+        command: String,
+        option: &'static str,
+    },
+}
+
+/// The options of a slash command or subcommand, looked up by name.
+struct CommandOptions<'a> {
+    command: &'a str,
+    options: &'a [CommandDataOption],
+}
+
+impl<'a> CommandOptions<'a> {
+    fn get(&self, name: &str) -> Option<&'a CommandDataOptionValue> {
+        self.options.iter().find(|option| option.name == name).map(|option| &option.value)
+    }
+
+    fn string(&self, name: &'static str) -> Result<Option<String>, CommandParseError> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(CommandDataOptionValue::String(value)) => Ok(Some(value.clone())),
+            Some(_) => Err(self.wrong_type(name)),
+        }
+    }
+
+    fn user(&self, name: &'static str) -> Result<Option<UserId>, CommandParseError> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(&CommandDataOptionValue::User(value)) => Ok(Some(value)),
+            Some(_) => Err(self.wrong_type(name)),
+        }
+    }
+
+    fn integer<T: TryFrom<i64>>(&self, name: &'static str) -> Result<Option<T>, CommandParseError> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(&CommandDataOptionValue::Integer(value)) => value.try_into().map(Some).map_err(|_| CommandParseError::OutOfRange { command: self.command.to_owned(), option: name }),
+            Some(_) => Err(self.wrong_type(name)),
+        }
+    }
+
+    fn boolean(&self, name: &'static str) -> Result<Option<bool>, CommandParseError> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(&CommandDataOptionValue::Boolean(value)) => Ok(Some(value)),
+            Some(_) => Err(self.wrong_type(name)),
+        }
+    }
+
+    fn role(&self, name: &'static str) -> Result<Option<RoleId>, CommandParseError> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(&CommandDataOptionValue::Role(value)) => Ok(Some(value)),
+            Some(_) => Err(self.wrong_type(name)),
+        }
+    }
+
+    fn required<T>(&self, name: &'static str, value: Option<T>) -> Result<T, CommandParseError> {
+        value.ok_or_else(|| CommandParseError::MissingOption { command: self.command.to_owned(), option: name })
+    }
+
+    fn wrong_type(&self, name: &'static str) -> CommandParseError {
+        CommandParseError::OptionType { command: self.command.to_owned(), option: name }
+    }
+
+    /// Returns the name and options of the subcommand or subcommand group that was used.
+    fn subcommand(&self) -> Result<(&'a str, Self), CommandParseError> {
+        match self.options {
+            [CommandDataOption { name, value: CommandDataOptionValue::SubCommand(options) | CommandDataOptionValue::SubCommandGroup(options), .. }] => Ok((name, Self { command: self.command, options })),
+            _ => Err(CommandParseError::Subcommand(self.command.to_owned())),
+        }
+    }
+}
+
+/// A slash command invocation with its options, parsed from the interaction data.
+enum SlashCommand {
+    BackupList {
+        member: Member,
+        world: Option<String>,
+    },
+    BackupNow {
+        member: Member,
+        world: Option<String>,
+    },
+    BackupRestore {
+        member: Member,
+        backup: i32,
+    },
+    EventCreate {
+        member: Member,
+        start: String,
+        end: String,
+        kind: cal::EventKind,
+    },
+    EventList,
+    EventRsvp {
+        event: i32,
+        attending: bool,
+    },
+    Iam {
+        member: Member,
+        role: RoleId,
+    },
+    Iamn {
+        member: Member,
+        role: RoleId,
+    },
+    Invite {
+        invitee: UserId,
+        reason: Option<String>,
+    },
+    LinkMinecraft {
+        name: String,
+    },
+    LinkTwitch,
+    Ping,
+    RoleMenu {
+        member: Member,
+    },
+    Status {
+        world: Option<String>,
+    },
+    Update {
+        version: Option<String>,
+    },
+    Veto {
+        invitee: UserId,
+    },
+    Whitelist {
+        admin: Member,
+        add: bool,
+        member: UserId,
+        reason: Option<String>,
+    },
+}
+
+impl SlashCommand {
+    /// Whether this command writes to the people database or the Minecraft worlds, so it may only be used in the main guild and not e.g. in a testing guild.
+    fn is_main_guild_only(&self) -> bool {
+        match self {
+            Self::BackupNow { .. } | Self::BackupRestore { .. } | Self::EventCreate { .. } | Self::Invite { .. } | Self::Update { .. } | Self::Veto { .. } | Self::Whitelist { .. } => true,
+            Self::BackupList { .. } | Self::EventList | Self::EventRsvp { .. } | Self::Iam { .. } | Self::Iamn { .. } | Self::LinkMinecraft { .. } | Self::LinkTwitch | Self::Ping | Self::RoleMenu { .. } | Self::Status { .. } => false,
+        }
+    }
+
+    fn parse(command_ids: CommandIds, interaction: &CommandInteraction) -> Result<Self, CommandParseError> {
+        let command = &*interaction.data.name;
+        let options = CommandOptions { command, options: &interaction.data.options };
+        let member = || interaction.member.as_deref().cloned().ok_or_else(|| CommandParseError::NotInGuild(command.to_owned()));
+        Ok(if interaction.data.id == command_ids.backup {
+            match options.subcommand()? {
+                ("list", options) => Self::BackupList { member: member()?, world: options.string("world")? },
+                ("now", options) => Self::BackupNow { member: member()?, world: options.string("world")? },
+                ("restore", options) => Self::BackupRestore { member: member()?, backup: options.required("backup", options.integer("backup")?)? },
+                _ => return Err(CommandParseError::Subcommand(command.to_owned())),
+            }
+        } else if interaction.data.id == command_ids.event {
+            match options.subcommand()? {
+                ("create", options) => {
+                    let (kind, options) = options.subcommand()?;
+                    let kind = match kind {
+                        "minigame" => cal::EventKind::Minigame { minigame: options.required("minigame", options.string("minigame")?)? },
+                        "renascence" => cal::EventKind::Renascence {
+                            settlement: options.required("settlement", options.string("settlement")?)?,
+                            hub_coords: [options.required("hub_x", options.integer("hub_x")?)?, options.required("hub_z", options.integer("hub_z")?)?],
+                        },
+                        "dragon-fight" => cal::EventKind::RenascenceDragonFight { settlement: options.required("settlement", options.string("settlement")?)? },
+                        "tour" => cal::EventKind::Tour { area: options.string("area")?, guests: options.user("guest")?.map(user::Id::Discord).into_iter().collect() },
+                        "usc" => cal::EventKind::Usc { season: options.required("season", options.integer("season")?)? },
+                        "other" => cal::EventKind::Other { title: options.required("title", options.string("title")?)?, location: options.string("location")? },
+                        _ => return Err(CommandParseError::Subcommand(command.to_owned())),
+                    };
+                    Self::EventCreate { member: member()?, start: options.required("start", options.string("start")?)?, end: options.required("end", options.string("end")?)?, kind }
+                }
+                ("list", _) => Self::EventList,
+                ("rsvp", options) => Self::EventRsvp { event: options.required("event", options.integer("event")?)?, attending: options.boolean("attending")?.unwrap_or(true) },
+                _ => return Err(CommandParseError::Subcommand(command.to_owned())),
+            }
+        } else if interaction.data.id == command_ids.iam {
+            Self::Iam { member: member()?, role: options.required("role", options.role("role")?)? }
+        } else if interaction.data.id == command_ids.iamn {
+            Self::Iamn { member: member()?, role: options.required("role", options.role("role")?)? }
+        } else if interaction.data.id == command_ids.invite {
+            Self::Invite { invitee: options.required("invitee", options.user("invitee")?)?, reason: options.string("reason")? }
+        } else if interaction.data.id == command_ids.link {
+            match options.subcommand()? {
+                ("minecraft", options) => Self::LinkMinecraft { name: options.required("name", options.string("name")?)? },
+                ("twitch", _) => Self::LinkTwitch,
+                _ => return Err(CommandParseError::Subcommand(command.to_owned())),
+            }
+        } else if interaction.data.id == command_ids.ping {
+            Self::Ping
+        } else if interaction.data.id == command_ids.role_menu {
+            Self::RoleMenu { member: member()? }
+        } else if interaction.data.id == command_ids.status {
+            Self::Status { world: options.string("world")? }
+        } else if interaction.data.id == command_ids.update {
+            Self::Update { version: options.string("version")? }
+        } else if interaction.data.id == command_ids.veto {
+            Self::Veto { invitee: options.required("invitee", options.user("invitee")?)? }
+        } else if interaction.data.id == command_ids.whitelist {
+            let (add, options) = match options.subcommand()? {
+                ("add", options) => (true, options),
+                ("remove", options) => (false, options),
+                _ => return Err(CommandParseError::Subcommand(command.to_owned())),
+            };
+            Self::Whitelist { admin: member()?, add, member: options.required("member", options.user("member")?)?, reason: options.string("reason")? }
+        } else {
+            return Err(CommandParseError::UnknownCommand(command.to_owned()))
+        })
+    }
+}
+
+/// The world named in a slash command option, defaulting to the world associated with the channel, or the main world. Returns the name as an error if there is no such world.
+async fn command_world(guild: &config::Guild, channel_id: ChannelId, name: Option<String>) -> Result<Result<systemd_minecraft::World, String>, systemd_minecraft::Error> {
+    Ok(if let Some(name) = name {
+        systemd_minecraft::World::all().await?.into_iter().find(|world| world.to_string() == name).ok_or(name)
+    } else if let Some((world, _)) = guild.world_channels.iter().find(|(_, chan_id)| **chan_id == channel_id) {
+        Ok(world.clone())
+    } else {
+        Ok(systemd_minecraft::World::default())
+    })
+}
+
+/// Reports an interaction that couldn't be handled in the dev channel of the guild where it happened, or of the main guild for interactions in DMs.
+async fn report_malformed_interaction(ctx: &Context, guild_id: Option<GuildId>, user: &serenity::model::user::User, e: impl std::fmt::Display) -> serenity::Result<()> {
+    let dev = match guild_config(ctx, guild_id).await {
+        Some(guild) => guild.dev_channel,
+        None => main_guild(ctx).await.dev_channel,
+    };
+    dev.say(ctx, MessageBuilder::default()
+        .push("malformed interaction from ")
+        .mention(user)
+        .push(": ")
+        .push_safe(e.to_string())
+        .build()
+    ).await?;
+    Ok(())
 }
 
 pub(crate) async fn configure_builder(discord_builder: serenity_utils::Builder, config: Config, shutdown: rocket::Shutdown) -> Result<serenity_utils::Builder, crate::Error> {
+    let http_client = reqwest::Client::builder()
+        .user_agent(concat!("wurstminebot/", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(30))
+        .use_rustls_tls()
+        .hickory_dns(true)
+        .https_only(true)
+        .build()?;
     discord_builder
-        .error_notifier(ErrorNotifier::Channel(DEV))
+        .error_notifier(ErrorNotifier::Channel(config.wurstminebot.main_guild.dev_channel))
         .on_ready(|ctx, ready| Box::pin(async move {
-            if ready.guilds.len() > 1 {
-                println!("error: multiple guilds found (wurstminebot's code currently assumes that it's only in the Wurstmineberg guild)"); //TODO return as Err?
-                serenity_utils::shut_down(&ctx).await;
+            let data = ctx.data.read().await;
+            let wurstminebot = &data.get::<Config>().expect("missing config").wurstminebot;
+            for guild in &ready.guilds {
+                if wurstminebot.guild(guild.id).is_none() {
+                    println!("warning: wurstminebot is in guild {} which is not configured, ignoring", guild.id);
+                }
             }
             Ok(())
         }))
         .on_message(true, |ctx, msg| Box::pin(async move {
             if msg.author.bot { return Ok(()) } // ignore bots to prevent message loops
-            if let Some((world, _)) = ctx.data.read().await.get::<Config>().expect("missing config").wurstminebot.world_channels.iter().find(|(_, chan_id)| **chan_id == msg.channel_id) {
-                if world.is_running().await? {
+            if msg.guild_id.is_none() {
+                crate::dm::from_discord(ctx, msg).await?;
+                return Ok(())
+            }
+            if let Some(world) = world_for_channel(ctx, msg.channel_id).await {
+                relay_to_minecraft(ctx, &world, msg, false).await?;
+            }
+            // notify mentioned people who are currently playing, but only for messages everyone in the main guild can read
+            if !msg.mentions.is_empty() && msg.guild_id == Some(main_guild(ctx).await.id) {
+                let Channel::Guild(channel) = msg.channel(ctx).await? else { return Ok(()) };
+                if !is_public(ctx, &channel).await? { return Ok(()) }
+                let (nicks, relayed_to) = {
+                    let data = ctx.data.read().await;
+                    let pool = data.get::<DbPool>().expect("missing database connection");
+                    let mut nicks = Vec::default();
+                    for mentioned in &msg.mentions {
+                        if mentioned.id == msg.author.id { continue }
+                        let Some(user) = User::from_discord(pool, mentioned.id).await? else { continue };
+                        let Some(nick) = user.minecraft_nick() else { continue };
+                        nicks.push(nick.to_owned());
+                    }
+                    (nicks, data.get::<Config>().expect("missing config").wurstminebot.world_for_channel(msg.channel_id).cloned())
+                };
+                if nicks.is_empty() { return Ok(()) }
+                let mut chat = Chat::from(format!("[Discord:#{}] ", channel.name));
+                chat.color(minecraft::chat::Color::Aqua);
+                chat.add_extra({
+                    let mut extra = Chat::from(msg.member.as_ref().and_then(|member| member.nick.as_deref()).unwrap_or(&msg.author.name));
+                    extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(msg.author.tag()))));
+                    extra
+                });
+                chat.add_extra(" mentioned you ");
+                chat.add_extra(link_component(format!("[open]"), Some(msg.link()), None));
+                for world in systemd_minecraft::World::all_running().await? {
+                    if relayed_to.as_ref() == Some(&world) { continue } // message has already been relayed to this world
+                    for nick in &nicks {
+                        world.tellraw(nick, &chat).await?;
+                    }
+                }
+            }
+            Ok(())
+        }))
+        .on_message_update(true, |ctx, _, _, event| Box::pin(async move {
+            if event.edited_timestamp.is_none() { return Ok(()) } // e.g. link previews being added, which aren't edits by the author
+            if event.author.as_ref().is_some_and(|author| author.bot) { return Ok(()) }
+            if let (Some(guild_id), Some(author), Some(content)) = (event.guild_id, &event.author, &event.content) {
+                if let Err(e) = crate::audit::record(ctx, guild_id, crate::audit::AuditEvent::MessageEdited { channel: event.channel_id, message: event.id, author: author.id, content: content.clone() }).await {
+                    eprintln!("failed to record message edit in moderation log: {e} ({e:?})");
+                }
+            }
+            if let Some(world) = world_for_channel(ctx, event.channel_id).await {
+                let msg = event.channel_id.message(ctx, event.id).await?;
+                if msg.author.bot { return Ok(()) }
+                relay_to_minecraft(ctx, &world, &msg, true).await?;
+            }
+            Ok(())
+        }))
+        .on_message_delete(|ctx, channel_id, message_id, guild_id| Box::pin(async move {
+            if let Some(guild_id) = guild_id {
+                if let Err(e) = crate::audit::record(ctx, guild_id, crate::audit::AuditEvent::MessageDeleted { channel: channel_id, message: message_id }).await {
+                    eprintln!("failed to record message deletion in moderation log: {e} ({e:?})");
+                }
+            }
+            if let Some(world) = world_for_channel(ctx, channel_id).await {
+                let was_relayed = {
+                    let mut data = ctx.data.write().await;
+                    let relayed = data.get_mut::<RelayedMessages>().expect("missing relayed messages");
+                    relayed.iter().position(|&relayed_id| relayed_id == message_id).map(|idx| relayed.remove(idx)).is_some()
+                };
+                if was_relayed && world.is_running().await? {
+                    // the message can't be removed from Minecraft chat, and its content and author are no longer available
                     let mut chat = Chat::from(format!(
-                        "[Discord:#{}",
-                        if let Channel::Guild(chan) = msg.channel(&ctx).await? { chan.name.clone() } else { format!("?") },
+                        "[Discord:#{}] (a message was deleted)",
+                        if let Channel::Guild(chan) = channel_id.to_channel(ctx).await? { chan.name.clone() } else { format!("?") },
                     ));
                     chat.color(minecraft::chat::Color::Aqua);
-                    if let Some(ref in_reply_to) = msg.referenced_message {
-                        chat.add_extra(", replying to ");
-                        chat.add_extra({
-                            let mut extra = Chat::from(in_reply_to.member.as_ref().and_then(|member| member.nick.as_deref()).unwrap_or(&in_reply_to.author.name));
-                            extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(in_reply_to.author.tag()))));
-                            extra
-                        });
-                    }
-                    chat.add_extra("] ");
-                    chat.add_extra({
-                        let mut extra = Chat::from(format!("<{}>", msg.member.as_ref().and_then(|member| member.nick.as_ref()).unwrap_or(&msg.author.name)));
-                        extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(msg.author.tag()))));
-                        extra
-                    });
-                    chat.add_extra(" ");
-                    discord_to_minecraft(&ctx, &msg, &mut chat, msg.parse()).await?;
-                    for attachment in &msg.attachments {
-                        chat.add_extra(" ");
-                        chat.add_extra({
-                            let mut extra = Chat::from(format!("[{}]", attachment.filename));
-                            extra.color(minecraft::chat::Color::Blue);
-                            extra.underlined();
-                            extra.on_click(minecraft::chat::ClickEvent::OpenUrl(attachment.url.clone()));
-                            extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(&*attachment.url))));
-                            extra
-                        });
-                    }
-                    match world.tellraw("@a", &chat).await {
-                        Ok(_) => {}
-                        Err(systemd_minecraft::Error::Rcon(rcon::Error::CommandTooLong)) => {
-                            let mut chat = Chat::from(format!(
-                                "[Discord:#{}] long message from ",
-                                if let Channel::Guild(chan) = msg.channel(&ctx).await? { chan.name.clone() } else { format!("?") },
-                            ));
-                            chat.color(minecraft::chat::Color::Aqua);
-                            chat.add_extra({
-                                let mut extra = Chat::from(msg.member.as_ref().and_then(|member| member.nick.as_deref()).unwrap_or(&msg.author.name));
-                                extra.on_hover(minecraft::chat::HoverEvent::ShowText(Box::new(Chat::from(msg.author.tag()))));
-                                extra
-                            });
-                            world.tellraw("@a", &chat).await?;
-                        }
-                        Err(e) => return Err(e.into()),
-                    }
+                    world.tellraw("@a", &chat).await?;
                 }
             }
             Ok(())
         }))
+        .on_guild_ban_addition(|ctx, guild_id, banned_user| Box::pin(async move {
+            if let Err(e) = crate::audit::record_ban(ctx, guild_id, banned_user.id, banned_user.name.clone()).await {
+                eprintln!("failed to record ban in moderation log: {e} ({e:?})");
+            }
+            Ok(())
+        }))
         .on_guild_create(false, |ctx, guild, _| Box::pin(async move {
+            if guild_config(ctx, Some(guild.id)).await.is_none() { return Ok(()) }
             let mut commands = Vec::default();
+            let backup = {
+                let idx = commands.len();
+                let world_option = || CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "world",
+                    "the world, defaults to this channel's world or the main world",
+                ).required(false).set_autocomplete(true);
+                commands.push(CreateCommand::new("backup")
+                    .kind(CommandType::ChatInput)
+                    .add_context(InteractionContext::Guild)
+                    .default_member_permissions(Permissions::ADMINISTRATOR)
+                    .description("Minecraft world backups (admins only)")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "list",
+                        "Show recent backups",
+                    )
+                        .add_sub_option(world_option())
+                    )
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "now",
+                        "Back up a world",
+                    )
+                        .add_sub_option(world_option())
+                    )
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "restore",
+                        "Replace a world with a backup, after confirmation",
+                    )
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::Integer,
+                            "backup",
+                            "the ID of the backup, as shown by /backup list",
+                        ).required(true).min_int_value(1))
+                    )
+                );
+                idx
+            };
+            let event = {
+                let idx = commands.len();
+                let event_kind = |name: &str, description: &str| CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    name,
+                    description,
+                )
+                    .add_sub_option(CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "start",
+                        "start time, e.g. 2026-10-24T19:00:00+02:00",
+                    ).required(true))
+                    .add_sub_option(CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "end",
+                        "end time, e.g. 2026-10-24T21:00:00+02:00",
+                    ).required(true));
+                commands.push(CreateCommand::new("event")
+                    .kind(CommandType::ChatInput)
+                    .add_context(InteractionContext::Guild)
+                    .description("Calendar events")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommandGroup,
+                        "create",
+                        "Add an event to the calendar (admins only)",
+                    )
+                        .add_sub_option(event_kind("minigame", "Minigame Night")
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::String,
+                                "minigame",
+                                "the minigame to play",
+                            ).required(true))
+                        )
+                        .add_sub_option(event_kind("renascence", "Renascence settlement opening")
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::String,
+                                "settlement",
+                                "the name of the settlement",
+                            ).required(true))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::Integer,
+                                "hub_x",
+                                "x coordinate of the settlement's Nether hub",
+                            ).required(true).min_number_value(i16::MIN.into()).max_number_value(i16::MAX.into())) // min_int_value only takes u64
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::Integer,
+                                "hub_z",
+                                "z coordinate of the settlement's Nether hub",
+                            ).required(true).min_number_value(i16::MIN.into()).max_number_value(i16::MAX.into())) // min_int_value only takes u64
+                        )
+                        .add_sub_option(event_kind("dragon-fight", "Renascence dragon fight")
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::String,
+                                "settlement",
+                                "the name of the settlement",
+                            ).required(true))
+                        )
+                        .add_sub_option(event_kind("tour", "Server tour")
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::String,
+                                "area",
+                                "the area to show, defaults to the whole server",
+                            ).required(false))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::User,
+                                "guest",
+                                "the person the tour is for",
+                            ).required(false))
+                        )
+                        .add_sub_option(event_kind("usc", "Ultra Softcore season")
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::Integer,
+                                "season",
+                                "the season number",
+                            ).required(true).min_int_value(1))
+                        )
+                        .add_sub_option(event_kind("other", "Any other event")
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::String,
+                                "title",
+                                "the name of the event",
+                            ).required(true))
+                            .add_sub_option(CreateCommandOption::new(
+                                CommandOptionType::String,
+                                "location",
+                                "where the event takes place",
+                            ).required(false))
+                        )
+                    )
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "list",
+                        "Show upcoming events",
+                    ))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "rsvp",
+                        "Say whether you will attend an event",
+                    )
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::Integer,
+                            "event",
+                            "the event",
+                        ).required(true).set_autocomplete(true))
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::Boolean,
+                            "attending",
+                            "whether you will attend, defaults to yes",
+                        ).required(false))
+                    )
+                );
+                idx
+            };
             let iam = {
                 let idx = commands.len();
                 commands.push(CreateCommand::new("iam")
@@ -381,6 +1191,50 @@ pub(crate) async fn configure_builder(discord_builder: serenity_utils::Builder,
                 );
                 idx
             };
+            let invite = {
+                let idx = commands.len();
+                commands.push(CreateCommand::new("invite")
+                    .kind(CommandType::ChatInput)
+                    .add_context(InteractionContext::Guild)
+                    .description("Invite someone to Wurstmineberg, starting the veto period")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::User,
+                        "invitee",
+                        "the person to invite",
+                    ).required(true))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "reason",
+                        "recorded in the person's status history",
+                    ).required(false))
+                );
+                idx
+            };
+            let link = {
+                let idx = commands.len();
+                commands.push(CreateCommand::new("link")
+                    .kind(CommandType::ChatInput)
+                    .add_context(InteractionContext::Guild)
+                    .description("Link another account to your Wurstmineberg profile")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "minecraft",
+                        "Link your Minecraft account, confirmed with a code you type in game",
+                    )
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "name",
+                            "your Minecraft username",
+                        ).required(true))
+                    )
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "twitch",
+                        "Link your Twitch account by signing in on the website",
+                    ))
+                );
+                idx
+            };
             let ping = {
                 let idx = commands.len();
                 commands.push(CreateCommand::new("ping")
@@ -390,6 +1244,30 @@ pub(crate) async fn configure_builder(discord_builder: serenity_utils::Builder,
                 );
                 idx
             };
+            let role_menu = {
+                let idx = commands.len();
+                commands.push(CreateCommand::new("rolemenu")
+                    .kind(CommandType::ChatInput)
+                    .add_context(InteractionContext::Guild)
+                    .default_member_permissions(Permissions::ADMINISTRATOR)
+                    .description("Post a menu of self-assignable roles in this channel")
+                );
+                idx
+            };
+            let status = {
+                let idx = commands.len();
+                commands.push(CreateCommand::new("status")
+                    .kind(CommandType::ChatInput)
+                    .add_context(InteractionContext::Guild)
+                    .description("Show whether a Minecraft world is running and who is online")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "world",
+                        "the world to check, defaults to this channel's world or the main world",
+                    ).required(false).set_autocomplete(true))
+                );
+                idx
+            };
             let update = {
                 let idx = commands.len();
                 commands.push(CreateCommand::new("update")
@@ -418,27 +1296,251 @@ pub(crate) async fn configure_builder(discord_builder: serenity_utils::Builder,
                 );
                 idx
             };
+            let whitelist = {
+                let idx = commands.len();
+                commands.push(CreateCommand::new("whitelist")
+                    .kind(CommandType::ChatInput)
+                    .add_context(InteractionContext::Guild)
+                    .default_member_permissions(Permissions::ADMINISTRATOR)
+                    .description("Add or remove someone from the Minecraft whitelist")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "add",
+                        "Whitelist someone as a member",
+                    )
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::User,
+                            "member",
+                            "the person to add",
+                        ).required(true))
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "reason",
+                            "recorded in the person's status history",
+                        ).required(false))
+                    )
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "remove",
+                        "Remove someone from the whitelist, making them a former member",
+                    )
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::User,
+                            "member",
+                            "the person to remove",
+                        ).required(true))
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "reason",
+                            "recorded in the person's status history",
+                        ).required(false))
+                    )
+                );
+                idx
+            };
             let commands = guild.set_commands(ctx, commands).await?;
-            ctx.data.write().await.insert::<CommandIds>(CommandIds {
+            ctx.data.write().await.get_mut::<CommandIds>().expect("missing command IDs").insert(guild.id, CommandIds {
+                backup: commands[backup].id,
+                event: commands[event].id,
                 iam: commands[iam].id,
                 iamn: commands[iamn].id,
+                invite: commands[invite].id,
+                link: commands[link].id,
                 ping: commands[ping].id,
+                role_menu: commands[role_menu].id,
+                status: commands[status].id,
                 update: commands[update].id,
                 veto: commands[veto].id,
+                whitelist: commands[whitelist].id,
             });
             Ok(())
         }))
         .on_interaction_create(|ctx, interaction| Box::pin(async move {
             match interaction {
                 Interaction::Command(interaction) => {
-                    if let Some(&command_ids) = ctx.data.read().await.get::<CommandIds>() {
-                        if interaction.data.id == command_ids.iam {
-                            let member = interaction.member.clone().expect("/iam called outside of a guild");
-                            let role_id = match interaction.data.options[0].value {
-                                CommandDataOptionValue::Role(role) => role,
-                                _ => panic!("unexpected slash command option type"),
+                    let Some(guild) = guild_config(ctx, interaction.guild_id).await else { return Ok(()) };
+                    let Some(command_ids) = ctx.data.read().await.get::<CommandIds>().expect("missing command IDs").get(&guild.id).copied() else { return Ok(()) };
+                    let command = match SlashCommand::parse(command_ids, &interaction) {
+                        Ok(command) => command,
+                        Err(e) => {
+                            interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                .ephemeral(true)
+                                .content("Sorry, something went wrong with this command. The admins have been notified.")
+                            )).await?;
+                            report_malformed_interaction(ctx, interaction.guild_id, &interaction.user, e).await?;
+                            return Ok(())
+                        }
+                    };
+                    if command.is_main_guild_only() && guild.id != main_guild(ctx).await.id {
+                        interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                            .ephemeral(true)
+                            .content("This command can only be used in the main Wurstmineberg server.")
+                        )).await?;
+                        return Ok(())
+                    }
+                    match command {
+                        SlashCommand::BackupList { member, world } => {
+                            if !member.roles.contains(&guild.admin_role) {
+                                interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                    .ephemeral(true)
+                                    .content("This command can only be used by admins.")
+                                )).await?;
+                                return Ok(())
+                            }
+                            let world = match command_world(&guild, interaction.channel_id, world).await? {
+                                Ok(world) => world,
+                                Err(name) => {
+                                    interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                        .ephemeral(true)
+                                        .content(MessageBuilder::default().push("There is no world named ").push_mono_safe(name).push('.').build())
+                                    )).await?;
+                                    return Ok(())
+                                }
+                            };
+                            let backups = {
+                                let data = ctx.data.read().await;
+                                let pool = data.get::<DbPool>().expect("missing database connection");
+                                crate::backup::Backup::recent(pool, Some(&world), 10).await?
+                            };
+                            let mut description = MessageBuilder::default();
+                            if backups.is_empty() {
+                                description.push("No backups yet.");
+                            }
+                            for backup in backups {
+                                description.push_line(backup.discord_line());
+                            }
+                            interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                .ephemeral(true)
+                                .embed(CreateEmbed::new()
+                                    .title(format!("recent backups of {world}"))
+                                    .url(uri!(crate::http::base_uri(), crate::backup::get).to_string())
+                                    .description(description.build())
+                                )
+                            )).await?;
+                        }
+                        SlashCommand::BackupNow { member, world } => {
+                            if !member.roles.contains(&guild.admin_role) {
+                                interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                    .ephemeral(true)
+                                    .content("This command can only be used by admins.")
+                                )).await?;
+                                return Ok(())
+                            }
+                            let world = match command_world(&guild, interaction.channel_id, world).await? {
+                                Ok(world) => world,
+                                Err(name) => {
+                                    interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                        .ephemeral(true)
+                                        .content(MessageBuilder::default().push("There is no world named ").push_mono_safe(name).push('.').build())
+                                    )).await?;
+                                    return Ok(())
+                                }
+                            };
+                            interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                .ephemeral(false)
+                                .content(MessageBuilder::default().push("Backing up ").push_safe(world.to_string()).push(" world…").build())
+                            )).await?;
+                            let reply = match crate::backup::create(ctx, &world, Some(member.user.id), "manual").await {
+                                Ok(id) => MessageBuilder::default().push("Backed up ").push_safe(world.to_string()).push(format!(" world as backup {id}.")).build(),
+                                Err(e) => MessageBuilder::default().push("Backup error: ").push_safe(e.to_string()).push(" (").push_mono_safe(format!("{:?}", e)).push(")").build(),
+                            };
+                            // backups can take longer than the interaction token is valid, so the result is posted as a new message
+                            interaction.channel_id.say(ctx, reply).await?;
+                        }
+                        SlashCommand::BackupRestore { member, backup } => {
+                            let message = if member.roles.contains(&guild.admin_role) && guild.id == main_guild(ctx).await.id {
+                                crate::backup::confirm_restore_message(ctx, backup).await?
+                            } else {
+                                CreateInteractionResponseMessage::new()
+                                    .ephemeral(true)
+                                    .content("Only admins of the main guild can restore backups.")
+                            };
+                            interaction.create_response(ctx, CreateInteractionResponse::Message(message)).await?;
+                        }
+                        SlashCommand::EventCreate { member, start, end, kind } => {
+                            let response = if !member.roles.contains(&guild.admin_role) {
+                                format!("This command can only be used by admins.")
+                            } else {
+                                match (start.parse::<DateWithOptionalTime>(), end.parse::<DateWithOptionalTime>()) {
+                                    (Ok(DateWithOptionalTime::DateTime(start)), Ok(DateWithOptionalTime::DateTime(end))) => if end <= start {
+                                        format!("The event must end after it starts.")
+                                    } else {
+                                        let data = ctx.data.read().await;
+                                        let pool = data.get::<DbPool>().expect("missing database connection");
+                                        let event = cal::Event::create(pool, start, end, kind).await?;
+                                        guild.general_channel.send_message(ctx, CreateMessage::new()
+                                            .content(format!("new event! Use `/event rsvp event:{}` to let others know you'll attend.", event.id))
+                                            .add_embed(event.discord_embed(pool).await?)
+                                        ).await?;
+                                        MessageBuilder::default().push("event created and announced in ").mention(&guild.general_channel).build()
+                                    },
+                                    _ => format!("Please specify the start and end times with a date, time, and UTC offset, e.g. 2026-10-24T19:00:00+02:00"),
+                                }
                             };
-                            let response = if !ctx.data.read().await.get::<Config>().expect("missing self-assignable roles list").wurstminebot.self_assignable_roles.contains(&role_id) {
+                            interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                .ephemeral(true)
+                                .content(response)
+                            )).await?;
+                        }
+                        SlashCommand::EventList => {
+                            let data = ctx.data.read().await;
+                            let pool = data.get::<DbPool>().expect("missing database connection");
+                            let events = cal::Event::upcoming(pool).await?;
+                            let mut description = MessageBuilder::default();
+                            if events.is_empty() {
+                                description.push("No upcoming events.");
+                            }
+                            for event in events.iter().take(10) { // keep within Discord's embed description length limit
+                                description.push(format!("`{}` ", event.id));
+                                event.title_discord(pool, &mut description).await?;
+                                description.push(format!(" <t:{}:F>", event.start_time.timestamp()));
+                                let attendees = event.attendees(pool).await?;
+                                if !attendees.is_empty() {
+                                    description.push(format!(" ({} attending)", attendees.len()));
+                                }
+                                description.push('\n');
+                            }
+                            if events.len() > 10 {
+                                description.push(format!("and {} more", events.len() - 10));
+                            }
+                            interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                .ephemeral(false)
+                                .allowed_mentions(CreateAllowedMentions::new()) // tour guests are mentioned in event titles
+                                .embed(CreateEmbed::new()
+                                    .title("upcoming events")
+                                    .colour(Colour(8794372))
+                                    .description(description.build())
+                                )
+                            )).await?;
+                        }
+                        SlashCommand::EventRsvp { event, attending } => {
+                            let data = ctx.data.read().await;
+                            let pool = data.get::<DbPool>().expect("missing database connection");
+                            let response = if let Some(event) = cal::Event::from_id(pool, event).await?.filter(|event| event.end_time > Utc::now()) {
+                                let changed = event.rsvp(pool, interaction.user.id, attending).await?;
+                                let mut builder = MessageBuilder::default();
+                                builder.push(match (attending, changed) {
+                                    (true, true) => "You're now attending ",
+                                    (true, false) => "You were already attending ",
+                                    (false, true) => "You're no longer attending ",
+                                    (false, false) => "You weren't attending ",
+                                });
+                                event.title_discord(pool, &mut builder).await?;
+                                builder.push('.');
+                                if attending {
+                                    builder.push(" You'll be pinged when it's about to start.");
+                                }
+                                builder.build()
+                            } else {
+                                format!("There is no upcoming event with this ID. Use /event list to see upcoming events.")
+                            };
+                            interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                .ephemeral(true)
+                                .content(response)
+                            )).await?;
+                        }
+                        SlashCommand::Iam { member, role: role_id } => {
+                            let response = if !guild.self_assignable_roles.contains(&role_id) {
                                 "this role is not self-assignable" //TODO (Discord feature request) list only self-assignable roles in autocomplete
                             } else if member.roles.contains(&role_id) {
                                 "you already have this role"
@@ -450,13 +1552,9 @@ pub(crate) async fn configure_builder(discord_builder: serenity_utils::Builder,
                                 .ephemeral(true)
                                 .content(response)
                             )).await?;
-                        } else if interaction.data.id == command_ids.iamn {
-                            let member = interaction.member.clone().expect("/iamn called outside of a guild");
-                            let role_id = match interaction.data.options[0].value {
-                                CommandDataOptionValue::Role(role) => role,
-                                _ => panic!("unexpected slash command option type"),
-                            };
-                            let response = if !ctx.data.read().await.get::<Config>().expect("missing self-assignable roles list").wurstminebot.self_assignable_roles.contains(&role_id) {
+                        }
+                        SlashCommand::Iamn { member, role: role_id } => {
+                            let response = if !guild.self_assignable_roles.contains(&role_id) {
                                 "this role is not self-assignable" //TODO (Discord feature request) list only self-assignable roles in autocomplete
                             } else if member.roles.contains(&role_id) {
                                 "you already don't have this role"
@@ -468,7 +1566,27 @@ pub(crate) async fn configure_builder(discord_builder: serenity_utils::Builder,
                                 .ephemeral(true)
                                 .content(response)
                             )).await?;
-                        } else if interaction.data.id == command_ids.ping {
+                        }
+                        SlashCommand::Invite { invitee, reason } => {
+                            // syncing the whitelist can take longer than Discord's response deadline
+                            interaction.create_response(ctx, CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new().ephemeral(true))).await?;
+                            let response = crate::invite::invite(ctx, &guild, interaction.user.id, invitee, reason).await?;
+                            interaction.edit_response(ctx, EditInteractionResponse::new().content(response)).await?;
+                        }
+                        SlashCommand::LinkMinecraft { name } => {
+                            let response = crate::link::start(ctx, interaction.user.id, &name).await?;
+                            interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                .ephemeral(true)
+                                .content(response)
+                            )).await?;
+                        }
+                        SlashCommand::LinkTwitch => {
+                            interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                .ephemeral(true)
+                                .content(format!("To link your Twitch account, sign in here: <{}>", crate::link::twitch_url()))
+                            )).await?;
+                        }
+                        SlashCommand::Ping => {
                             interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
                                 .ephemeral(true)
                                 .content({
@@ -480,63 +1598,184 @@ pub(crate) async fn configure_builder(discord_builder: serenity_utils::Builder,
                                     }
                                 })
                             )).await?;
-                        } else if interaction.data.id == command_ids.update {
-                            if let Some((world, _)) = ctx.data.read().await.get::<Config>().expect("missing config").wurstminebot.world_channels.iter().find(|(_, chan_id)| **chan_id == interaction.channel_id) {
-                                let version_spec = if let Some(option) = interaction.data.options.get(0) {
-                                    match &option.value {
-                                        CommandDataOptionValue::String(version) => systemd_minecraft::VersionSpec::Exact(version.clone()),
-                                        _ => panic!("unexpected slash command option type"),
-                                    }
-                                } else {
-                                    systemd_minecraft::VersionSpec::LatestRelease
-                                };
-                                if *world == systemd_minecraft::World::default() {
+                        }
+                        SlashCommand::RoleMenu { member } => {
+                            let response = if !member.roles.contains(&guild.admin_role) {
+                                "This command can only be used by admins."
+                            } else {
+                                crate::role_menu::post(ctx, &guild, interaction.channel_id).await?;
+                                "role menu posted"
+                            };
+                            interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                .ephemeral(true)
+                                .content(response)
+                            )).await?;
+                        }
+                        SlashCommand::Status { world } => {
+                            let world = match command_world(&guild, interaction.channel_id, world).await? {
+                                Ok(world) => world,
+                                Err(name) => {
                                     interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
-                                        .ephemeral(false)
-                                        .content(MessageBuilder::default().push("Backing up ").push_safe(world.to_string()).push(" world…").build())
+                                        .ephemeral(true)
+                                        .content(MessageBuilder::default().push("There is no world named ").push_mono_safe(name).push('.').build())
                                     )).await?;
-                                    Command::new(Path::new(BASE_PATH).join("bin").join("wurstminebackup")).check("wurstminebackup").await?;
-                                    interaction.channel_id.say(ctx, MessageBuilder::default().push("Updating ").push_safe(world.to_string()).push(" world…").build()).await?;
+                                    return Ok(())
+                                }
+                            };
+                            let data = ctx.data.read().await;
+                            let pool = data.get::<DbPool>().expect("missing database connection");
+                            let info = crate::api::WorldInfo::with_players(pool, &world).await?;
+                            let mut players = MessageBuilder::default();
+                            for (idx, id) in info.list.unwrap_or_default().into_iter().enumerate() {
+                                if idx > 0 {
+                                    players.push(", ");
+                                }
+                                players.mention_user(&User::from_id(pool, id).await?);
+                            }
+                            let players = players.build();
+                            interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                .ephemeral(false)
+                                .embed(CreateEmbed::new()
+                                    .title(if info.main { format!("{world} (main world)") } else { world.to_string() })
+                                    .colour(if info.running { Colour::DARK_GREEN } else { Colour::RED })
+                                    .field("status", if info.running { "running" } else { "stopped" }, true)
+                                    .field("version", info.version.as_deref().unwrap_or("unknown"), true)
+                                    .field("online players", if players.is_empty() { "none" } else { &players }, false)
+                                )
+                            )).await?;
+                        }
+                        SlashCommand::Update { version } => {
+                            if let Some((world, _)) = guild.world_channels.iter().find(|(_, chan_id)| **chan_id == interaction.channel_id) {
+                                if let Some(_in_progress) = crate::update::InProgress::start(world) {
+                                    interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                        .ephemeral(true)
+                                        .content("Update started, progress will be shown below.")
+                                    )).await?;
+                                    crate::update::run(ctx, interaction.channel_id, world, interaction.user.id, version).await?;
                                 } else {
                                     interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
-                                        .ephemeral(false)
-                                        .content(MessageBuilder::default().push("Updating ").push_safe(world.to_string()).push(" world…").build())
+                                        .ephemeral(true)
+                                        .content("An update is already running for this world.")
                                     )).await?;
                                 }
-                                let reply = match world.update(version_spec).await {
-                                    Ok(()) => format!("Done!"),
-                                    Err(e) => MessageBuilder::default().push("World update error: ").push_safe(e.to_string()).push(" (").push_mono_safe(format!("{:?}", e)).push(")").build(),
-                                };
-                                interaction.channel_id.say(ctx, reply).await?;
                             } else {
                                 interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
                                     .ephemeral(true)
                                     .content("This channel has no associated Minecraft world.")
                                 )).await?;
                             }
-                        } else if interaction.data.id == command_ids.veto {
-                            //TODO only allow current members to use this command
-                            let user_id = match interaction.data.options[0].value {
-                                CommandDataOptionValue::User(user) => user,
-                                _ => panic!("unexpected slash command option type"),
+                        }
+                        SlashCommand::Veto { invitee } => {
+                            // syncing the whitelist can take longer than Discord's response deadline
+                            interaction.create_response(ctx, CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new().ephemeral(true))).await?;
+                            let response = crate::invite::veto(ctx, &guild, interaction.user.id, invitee).await?;
+                            interaction.edit_response(ctx, EditInteractionResponse::new().content(response)).await?;
+                        }
+                        SlashCommand::Whitelist { admin, add, member, reason } => {
+                            if !admin.roles.contains(&guild.admin_role) {
+                                interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                    .ephemeral(true)
+                                    .content("This command can only be used by admins.")
+                                )).await?;
+                                return Ok(())
+                            }
+                            let (response, changed) = {
+                                let data = ctx.data.read().await;
+                                let pool = data.get::<DbPool>().expect("missing database connection");
+                                if let Some(mut person) = User::from_discord(pool, member).await? {
+                                    if person.is_whitelisted() == add {
+                                        (MessageBuilder::default().mention_user(&person).push(if add { " is already whitelisted" } else { " is already not whitelisted" }).build(), false)
+                                    } else {
+                                        let by = User::from_discord(pool, admin.user.id).await?.map(|admin| admin.id);
+                                        let status = if add { user::Status::Later } else { user::Status::Former };
+                                        person.data.status_history.push(user::StatusHistoryItem::now(status, by, reason));
+                                        person.save_data(pool).await?;
+                                        guild.dev_channel.say(ctx, MessageBuilder::default()
+                                            .mention(&admin.user)
+                                            .push(" changed the status of ")
+                                            .mention_user(&person)
+                                            .push(" to ")
+                                            .push(status.as_str())
+                                            .build()
+                                        ).await?;
+                                        let mut builder = MessageBuilder::default();
+                                        builder.mention_user(&person);
+                                        builder.push(if add { " is now a member" } else { " is now a former member" });
+                                        if person.minecraft_uuid().is_none() {
+                                            builder.push(", but they have no Minecraft account on file, so the whitelist was not changed");
+                                        }
+                                        (builder.build(), true)
+                                    }
+                                } else {
+                                    (MessageBuilder::default().mention(&member).push(" is not in the people database").build(), false)
+                                }
                             };
-                            //TODO validate veto period, kick person from guild and remove from whitelist
-                            GENERAL.say(ctx, MessageBuilder::default()
-                                .push("invite for ")
-                                .mention(&user_id)
-                                .push(" has been vetoed")
-                                .build()
-                            ).await?;
+                            // reply first since syncing the whitelist can take longer than Discord's response deadline
                             interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
                                 .ephemeral(true)
-                                .content(MessageBuilder::new().push("message posted in ").mention(&GENERAL).build())
+                                .content(response)
                             )).await?;
+                            if changed {
+                                crate::whitelist::sync(ctx).await?;
+                            }
+                        }
+                    }
+                }
+                Interaction::Autocomplete(interaction) => {
+                    let command_ids = match interaction.guild_id {
+                        Some(guild_id) => ctx.data.read().await.get::<CommandIds>().expect("missing command IDs").get(&guild_id).copied(),
+                        None => None,
+                    };
+                    if let Some(command_ids) = command_ids {
+                        if interaction.data.id == command_ids.status || interaction.data.id == command_ids.backup {
+                            let prefix = interaction.data.autocomplete().map(|option| option.value.to_lowercase()).unwrap_or_default();
+                            let mut response = CreateAutocompleteResponse::new();
+                            for world in systemd_minecraft::World::all().await?.into_iter().map(|world| world.to_string()).filter(|world| world.to_lowercase().starts_with(&prefix)).sorted().take(25) { // Discord allows at most 25 choices
+                                response = response.add_string_choice(world.clone(), world);
+                            }
+                            interaction.create_response(ctx, CreateInteractionResponse::Autocomplete(response)).await?;
+                        } else if interaction.data.id == command_ids.event {
+                            let query = interaction.data.autocomplete().map(|option| option.value.to_lowercase()).unwrap_or_default();
+                            let data = ctx.data.read().await;
+                            let pool = data.get::<DbPool>().expect("missing database connection");
+                            let mut response = CreateAutocompleteResponse::new();
+                            let mut num_choices = 0;
+                            for event in cal::Event::upcoming(pool).await? {
+                                let title = event.title(pool).await?;
+                                if title.to_lowercase().contains(&query) || event.id.to_string().starts_with(&query) {
+                                    let name = format!("{} ({})", title, event.start_time.format("%Y-%m-%d"));
+                                    response = response.add_int_choice(name.chars().take(100).collect::<String>(), event.id.into()); // Discord allows at most 100 characters per choice name
+                                    num_choices += 1;
+                                    if num_choices >= 25 { break } // Discord allows at most 25 choices
+                                }
+                            }
+                            interaction.create_response(ctx, CreateInteractionResponse::Autocomplete(response)).await?;
                         } else {
-                            panic!("unexpected slash command")
+                            interaction.create_response(ctx, CreateInteractionResponse::Autocomplete(CreateAutocompleteResponse::new())).await?;
+                            report_malformed_interaction(ctx, interaction.guild_id, &interaction.user, format!("autocomplete for unexpected slash command /{}", interaction.data.name)).await?;
                         }
                     }
                 }
-                Interaction::Component(_) => panic!("received message component interaction even though no message components are registered"),
+                Interaction::Component(interaction) => {
+                    let custom_id = &interaction.data.custom_id;
+                    if let Some(backup_id) = crate::backup::parse_custom_id(custom_id) {
+                        crate::backup::handle_restore_button(ctx, &interaction, backup_id).await?;
+                        return Ok(())
+                    }
+                    let response = match (crate::role_menu::parse_custom_id(custom_id), crate::member::parse_custom_id(custom_id), &interaction.member) {
+                        (Some(role_id), _, Some(member)) => crate::role_menu::toggle(ctx, member, role_id).await?.to_owned(),
+                        (_, Some(user_id), Some(admin)) => crate::member::confirm_former(ctx, admin, user_id).await?,
+                        _ => {
+                            // e.g. a button from a message posted by an older version of wurstminebot
+                            report_malformed_interaction(ctx, interaction.guild_id, &interaction.user, format!("unexpected message component {custom_id:?}")).await?;
+                            format!("Sorry, this button is no longer supported. The admins have been notified.")
+                        }
+                    };
+                    interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                        .ephemeral(true)
+                        .content(response)
+                    )).await?;
+                }
                 _ => {}
             }
             Ok(())
@@ -545,6 +1784,12 @@ pub(crate) async fn configure_builder(discord_builder: serenity_utils::Builder,
         .event_handler(serenity_utils::handler::voice_state_exporter::<VoiceStateExporter>())
         .data::<Config>(config)
         .data::<DbPool>(PgPool::connect_with(PgConnectOptions::default().database("wurstmineberg").application_name("wurstminebot")).await?)
+        .data::<CommandIds>(HashMap::default())
+        .data::<RelayedMessages>(VecDeque::default())
+        .data::<crate::link::PendingLinks>(HashMap::default())
+        .data::<crate::voice::VoiceState>(tokio::sync::watch::Sender::new(None))
+        .data::<crate::link::ProfileApi>(crate::link::ProfileApi::Mojang(http_client.clone()))
+        .data::<HttpClient>(http_client)
         .task(|ctx_fut, notify_thread_crash| async move {
             if let Err(e) = cal::notifications(ctx_fut).await {
                 eprintln!("{}", e);
@@ -552,11 +1797,41 @@ pub(crate) async fn configure_builder(discord_builder: serenity_utils::Builder,
             }
         })
         .task(|ctx_fut, notify_thread_crash| async move {
-            // follow the Minecraft log
-            let Err(e) = crate::log::handle(ctx_fut).await;
+            // follow the Minecraft logs
+            let Err(e) = crate::log::handle(ctx_fut, &notify_thread_crash).await;
             eprintln!("{}", e);
             notify_thread_crash(format!("log"), Box::new(e), None).await;
         })
+        .task(|ctx_fut, notify_thread_crash| async move {
+            // promote invitees whose veto period has ended
+            loop {
+                if let Err(e) = crate::invite::promote_expired(&*ctx_fut.read().await).await {
+                    eprintln!("{} ({:?})", e, e);
+                    notify_thread_crash(format!("invite promotion"), Box::new(e), Some(INVITE_CHECK_INTERVAL)).await;
+                }
+                sleep(INVITE_CHECK_INTERVAL).await;
+            }
+        })
+        .task(|ctx_fut, notify_thread_crash| async move {
+            // periodically sync the Minecraft whitelists with the people database, e.g. after a world was started
+            loop {
+                if let Err(e) = crate::whitelist::sync(&*ctx_fut.read().await).await {
+                    eprintln!("{} ({:?})", e, e);
+                    notify_thread_crash(format!("whitelist sync"), Box::new(e), Some(WHITELIST_SYNC_INTERVAL)).await;
+                }
+                sleep(WHITELIST_SYNC_INTERVAL).await;
+            }
+        })
+        .task(|ctx_fut, notify_thread_crash| async move {
+            // delete backups older than the configured retention period
+            loop {
+                if let Err(e) = crate::backup::prune(&*ctx_fut.read().await).await {
+                    eprintln!("{} ({:?})", e, e);
+                    notify_thread_crash(format!("backup pruning"), Box::new(e), Some(BACKUP_PRUNE_INTERVAL)).await;
+                }
+                sleep(BACKUP_PRUNE_INTERVAL).await;
+            }
+        })
         .task(|ctx_fut, notify_thread_crash| async move {
             // listen for Twitch chat messages
             let mut last_crash = Instant::now();
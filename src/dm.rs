@@ -0,0 +1,83 @@
+use {
+    minecraft::chat::Chat,
+    rocket::uri,
+    serenity::{
+        all::MessageBuilder,
+        model::prelude::*,
+        prelude::*,
+    },
+    sqlx::PgPool,
+    crate::{
+        discord::{
+            DbPool,
+            online_players,
+        },
+        http::base_uri,
+        user::User,
+    },
+};
+#[cfg(not(target_os = "linux"))] use crate::systemd_minecraft;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Minecraft(#[from] systemd_minecraft::Error),
+    #[error(transparent)] Serenity(#[from] serenity::Error),
+    #[error(transparent)] Sql(#[from] sqlx::Error),
+}
+
+/// Splits a DM into the recipient's name and the message.
+fn parse(text: &str) -> Option<(&str, &str)> {
+    let (name, msg) = text.trim().split_once(char::is_whitespace)?;
+    let msg = msg.trim();
+    (!msg.is_empty()).then_some((name.trim_start_matches('@'), msg))
+}
+
+/// Finds a person by Wurstmineberg ID, Minecraft nickname, or Discord username, in that order.
+async fn resolve(pool: &PgPool, name: &str) -> sqlx::Result<Option<User>> {
+    if let Some(user) = User::from_wmbid(pool, name).await? {
+        return Ok(Some(user))
+    }
+    if let Some(user) = User::from_minecraft_nick(pool, name).await? {
+        return Ok(Some(user))
+    }
+    User::from_tag(pool, name, None).await
+}
+
+fn settings_url() -> String {
+    uri!(base_uri(), crate::user::preferences_get(_)).to_string()
+}
+
+/// Handles a DM sent to the bot on Discord, which is forwarded to the Minecraft player it's addressed to.
+pub(crate) async fn from_discord(ctx: &Context, msg: &Message) -> Result<(), Error> {
+    let reply = {
+        let data = ctx.data.read().await;
+        let pool = data.get::<DbPool>().expect("missing database connection");
+        match (User::from_discord(pool, msg.author.id).await?.filter(|sender| sender.allows_minecraft_dms()), parse(&msg.content)) {
+            (None, _) => format!("To send DMs to Minecraft players, enable Minecraft DMs in your settings at <{}>", settings_url()),
+            (Some(_), None) => format!("To send a DM to a Minecraft player, send their name followed by your message."),
+            (Some(sender), Some((name, text))) => match resolve(pool, name).await?.filter(|recipient| recipient.allows_minecraft_dms()) {
+                Some(recipient) if recipient.minecraft_nick().is_some() => {
+                    let nick = recipient.minecraft_nick().expect("checked above");
+                    let mut chat = Chat::from(format!("[DM from {sender}] "));
+                    chat.color(minecraft::chat::Color::Aqua);
+                    chat.add_extra(text);
+                    let mut delivered = false;
+                    for world in systemd_minecraft::World::all_running().await? {
+                        if online_players(&world).await?.is_some_and(|players| players.iter().any(|(player, _)| player.eq_ignore_ascii_case(nick))) {
+                            world.tellraw(nick, &chat).await?;
+                            delivered = true;
+                        }
+                    }
+                    if delivered {
+                        format!("message delivered")
+                    } else {
+                        MessageBuilder::default().push_safe(recipient.to_string()).push(" is not currently playing.").build()
+                    }
+                }
+                _ => MessageBuilder::default().push_safe(name).push(" can't receive DMs in Minecraft.").build(),
+            },
+        }
+    };
+    msg.channel_id.say(ctx, reply).await?;
+    Ok(())
+}
@@ -55,6 +55,7 @@ use {
         night_report,
         static_url,
         user::User,
+        voice::VoiceState,
     },
 };
 #[cfg(not(target_os = "linux"))] use crate::systemd_minecraft;
@@ -279,7 +280,13 @@ enum IndexError {
 }
 
 #[rocket::get("/")]
-async fn index(db_pool: &State<PgPool>, me: Option<User>, uri: Origin<'_>) -> Result<RawHtml<String>, IndexError> {
+async fn index(db_pool: &State<PgPool>, discord_ctx: &State<RwFuture<DiscordCtx>>, me: Option<User>, uri: Origin<'_>) -> Result<RawHtml<String>, IndexError> {
+    // like the voice state API, only shown to signed-in users
+    let voice_channels = if me.is_some() {
+        discord_ctx.read().await.data.read().await.get::<VoiceState>().expect("missing voice state").borrow().clone()
+    } else {
+        None
+    };
     Ok(page(&me, &uri, PageStyle::default(), "Wurstmineberg", Tab::Home, html! {
         div(class = "panel panel-default") {
             div(class = "panel-heading") {
@@ -345,6 +352,38 @@ async fn index(db_pool: &State<PgPool>, me: Option<User>, uri: Origin<'_>) -> Re
                 }
             }
         }
+        @if let Some(voice_channels) = voice_channels {
+            div(class = "panel panel-default", id = "voice") {
+                div(class = "panel-heading") {
+                    h3(class = "panel-title") : "Who's in voice";
+                }
+                div(class = "panel-body") {
+                    @let occupied = voice_channels.iter().filter(|channel| !channel.members.is_empty()).collect::<Vec<_>>();
+                    @if occupied.is_empty() {
+                        p : "Nobody is in a voice channel on our Discord server right now.";
+                    } else {
+                        ul {
+                            @for channel in occupied {
+                                li {
+                                    strong : &channel.name;
+                                    : ": ";
+                                    @for (idx, member) in channel.members.iter().enumerate() {
+                                        @if idx > 0 {
+                                            : ", ";
+                                        }
+                                        @if let Some(user) = User::from_discord(&**db_pool, member.id).await? {
+                                            : user;
+                                        } else {
+                                            : &member.username;
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
         div(class = "row") {
             div(class = "col-md-4") {
                 h2(id = "overview") {
@@ -704,11 +743,13 @@ pub(crate) async fn rocket(config: Config, discord_ctx: RwFuture<DiscordCtx>, ht
             crate::api::player_data_json,
             crate::api::world_status,
             crate::api::websocket,
+            crate::audit::get,
             crate::auth::discord_callback,
             crate::auth::twitch_callback,
             crate::auth::discord_login,
             crate::auth::twitch_login,
             crate::auth::logout,
+            crate::backup::get,
             crate::stats::get,
             crate::user::list,
             crate::user::profile,
@@ -0,0 +1,139 @@
+use {
+    chrono::{
+        TimeDelta,
+        prelude::*,
+    },
+    futures::{
+        future,
+        stream::TryStreamExt as _,
+    },
+    serenity::{
+        all::MessageBuilder,
+        model::prelude::*,
+        prelude::*,
+    },
+    serenity_utils::handler::user_list::ExporterMethods as _,
+    crate::{
+        config,
+        discord::{
+            DbPool,
+            MessageBuilderExt as _,
+            UserListExporter,
+        },
+        user::{
+            Status,
+            StatusHistoryItem,
+            User,
+        },
+    },
+};
+
+/// How long members have to veto an invite before the invitee automatically becomes a member.
+const VETO_PERIOD: TimeDelta = TimeDelta::weeks(1);
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Serenity(#[from] serenity::Error),
+    #[error(transparent)] Sql(#[from] sqlx::Error),
+    #[error(transparent)] Whitelist(#[from] crate::whitelist::Error),
+    #[error("newly created person not found in database")]
+    CreatedUserNotFound,
+    #[error("failed to export Discord data of invitee: {0}")]
+    Export(Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// Invites the given Discord user on behalf of the given inviter in the given guild, returning the reply to the inviter.
+pub(crate) async fn invite(ctx: &Context, guild: &config::Guild, inviter: UserId, invitee: UserId, reason: Option<String>) -> Result<String, Error> {
+    {
+        let data = ctx.data.read().await;
+        let pool = data.get::<DbPool>().expect("missing database connection");
+        let Some(inviter) = User::from_discord(pool, inviter).await?.filter(|inviter| inviter.is_member()) else {
+            return Ok(format!("Only members can invite people."))
+        };
+        let mut person = if let Some(person) = User::from_discord(pool, invitee).await? {
+            person
+        } else {
+            let member = match guild.id.member(ctx, invitee).await {
+                Ok(member) => member,
+                Err(serenity::Error::Http(e)) if e.status_code().is_some_and(|status| status == serenity::http::StatusCode::NOT_FOUND) => return Ok(format!("Please ask them to join this Discord server first.")),
+                Err(e) => return Err(e.into()),
+            };
+            User::create_from_discord(pool, invitee).await?;
+            UserListExporter::upsert(ctx, &member).await.map_err(Error::Export)?;
+            User::from_discord(pool, invitee).await?.ok_or(Error::CreatedUserNotFound)?
+        };
+        match person.status() {
+            Some(Status::Founding | Status::Later) => return Ok(MessageBuilder::default().mention_user(&person).push(" is already a member.").build()),
+            Some(Status::Invited) => return Ok(MessageBuilder::default().mention_user(&person).push(" is already invited.").build()),
+            Some(Status::Former | Status::Vetoed | Status::Guest) | None => {}
+        }
+        let deadline = Utc::now() + VETO_PERIOD;
+        let mut item = StatusHistoryItem::now(Status::Invited, Some(inviter.id.clone()), reason);
+        item.deadline = Some(deadline);
+        person.data.status_history.push(item);
+        person.save_data(pool).await?;
+        guild.general_channel.say(ctx, MessageBuilder::default()
+            .mention_user(&inviter)
+            .push(" has invited ")
+            .mention_user(&person)
+            .push(format!(". Members can anonymously veto this invite using /veto until <t:{}:F>.", deadline.timestamp()))
+            .build()
+        ).await?;
+    }
+    crate::whitelist::sync(ctx).await?;
+    Ok(MessageBuilder::new().push("invite posted in ").mention(&guild.general_channel).build())
+}
+
+/// Vetoes the invite of the given Discord user on behalf of the given member in the given guild, returning the reply to the member.
+pub(crate) async fn veto(ctx: &Context, guild: &config::Guild, member: UserId, invitee: UserId) -> Result<String, Error> {
+    {
+        let data = ctx.data.read().await;
+        let pool = data.get::<DbPool>().expect("missing database connection");
+        if !User::from_discord(pool, member).await?.is_some_and(|member| member.is_member()) {
+            return Ok(format!("Only members can veto invites."))
+        }
+        let Some(mut person) = User::from_discord(pool, invitee).await? else {
+            return Ok(format!("This person is not invited."))
+        };
+        match person.veto_deadline() {
+            Some(deadline) if deadline > Utc::now() => {}
+            Some(_) => return Ok(format!("The veto period for this invite has ended.")),
+            None => return Ok(format!("This person is not invited.")),
+        }
+        person.data.status_history.push(StatusHistoryItem::now(Status::Vetoed, None, None)); // vetoes are anonymous
+        person.save_data(pool).await?;
+        guild.general_channel.say(ctx, MessageBuilder::default()
+            .push("invite for ")
+            .mention(&invitee)
+            .push(" has been vetoed")
+            .build()
+        ).await?;
+        match guild.id.kick_with_reason(ctx, invitee, "invite vetoed").await {
+            Ok(()) => {}
+            Err(serenity::Error::Http(e)) if e.status_code().is_some_and(|status| status == serenity::http::StatusCode::NOT_FOUND) => {} // already left the server
+            Err(e) => return Err(e.into()),
+        }
+    }
+    crate::whitelist::sync(ctx).await?;
+    Ok(MessageBuilder::new().push("message posted in ").mention(&guild.general_channel).build())
+}
+
+/// Promotes invitees whose veto period has ended to members.
+pub(crate) async fn promote_expired(ctx: &Context) -> Result<(), Error> {
+    let general = crate::discord::main_guild(ctx).await.general_channel;
+    let data = ctx.data.read().await;
+    let pool = data.get::<DbPool>().expect("missing database connection");
+    let now = Utc::now();
+    let expired = User::all(pool).try_filter(|person| future::ready(person.veto_deadline().is_some_and(|deadline| deadline <= now))).try_collect::<Vec<_>>().await?;
+    for mut person in expired {
+        person.data.status_history.push(StatusHistoryItem::now(Status::Later, None, Some(format!("veto period ended"))));
+        person.save_data(pool).await?;
+        general.say(ctx, MessageBuilder::default()
+            .push("the veto period for ")
+            .mention_user(&person)
+            .push(" has ended without a veto, welcome to Wurstmineberg!")
+            .build()
+        ).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,226 @@
+use {
+    std::{
+        collections::HashMap,
+        time::Duration,
+    },
+    lazy_regex::regex_is_match,
+    rand::{
+        prelude::*,
+        rng,
+    },
+    rocket::uri,
+    serde::Deserialize,
+    serenity::{
+        all::{
+            CreateMessage,
+            MessageBuilder,
+        },
+        model::prelude::*,
+        prelude::*,
+    },
+    tokio::time::Instant,
+    uuid::Uuid,
+    wheel::traits::ReqwestResponseExt as _,
+    crate::{
+        discord::{
+            DbPool,
+            online_players,
+        },
+        http::base_uri,
+        user::User,
+    },
+};
+#[cfg(not(target_os = "linux"))] use crate::systemd_minecraft;
+
+/// How long a player has to type the one-time code in Minecraft chat after using `/link minecraft`.
+const CODE_VALIDITY: Duration = Duration::from_mins(10);
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Minecraft(#[from] systemd_minecraft::Error),
+    #[error(transparent)] Reqwest(#[from] reqwest::Error),
+    #[error(transparent)] Serenity(#[from] serenity::Error),
+    #[error(transparent)] Sql(#[from] sqlx::Error),
+    #[error(transparent)] Wheel(#[from] wheel::Error),
+    #[error("pending Minecraft account link for a person who is not in the database")]
+    UnknownUser,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct Profile {
+    id: Uuid,
+    name: String,
+}
+
+/// Looks up Minecraft accounts by name.
+#[derive(Clone)]
+pub(crate) enum ProfileApi {
+    Mojang(reqwest::Client),
+    /// A fixed set of accounts, for testing without network access.
+    #[cfg(test)]
+    Fixed(Vec<Profile>),
+}
+
+impl ProfileApi {
+    async fn lookup(&self, name: &str) -> Result<Option<Profile>, Error> {
+        match self {
+            Self::Mojang(http_client) => {
+                let response = http_client.get(format!("https://api.mojang.com/users/profiles/minecraft/{name}"))
+                    .send().await?;
+                if matches!(response.status(), reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::NO_CONTENT) {
+                    Ok(None)
+                } else {
+                    Ok(Some(response.detailed_error_for_status().await?.json_with_text_in_error().await?))
+                }
+            }
+            #[cfg(test)]
+            Self::Fixed(profiles) => Ok(profiles.iter().find(|profile| profile.name.eq_ignore_ascii_case(name)).cloned()),
+        }
+    }
+}
+
+impl TypeMapKey for ProfileApi {
+    type Value = Self;
+}
+
+pub(crate) struct PendingLink {
+    discord_id: UserId,
+    profile: Profile,
+    code: String,
+    expires: Instant,
+}
+
+impl PendingLink {
+    /// Whether the given chat message from the given player confirms this link.
+    ///
+    /// The player's UUID must be known, since otherwise anyone using the same name, e.g. on an offline mode server, could confirm the link.
+    fn is_confirmed_by(&self, player_uuid: Option<Uuid>, msg: &str, now: Instant) -> bool {
+        now < self.expires
+        && player_uuid == Some(self.profile.id)
+        && msg.trim() == self.code
+    }
+}
+
+/// Minecraft account links waiting for the player to type their code in chat, keyed by lowercase Minecraft nickname.
+pub(crate) enum PendingLinks {}
+
+impl TypeMapKey for PendingLinks {
+    type Value = HashMap<String, PendingLink>;
+}
+
+/// Starts linking the Minecraft account with the given name to the given Discord user, returning the reply to the user.
+pub(crate) async fn start(ctx: &Context, discord_id: UserId, name: &str) -> Result<String, Error> {
+    if !regex_is_match!("^[A-Za-z0-9_]{1,16}$", name) {
+        return Ok(MessageBuilder::default().push_mono_safe(name).push(" is not a valid Minecraft username.").build())
+    }
+    let profile = {
+        let (profile_api, pool) = {
+            let data = ctx.data.read().await;
+            (data.get::<ProfileApi>().expect("missing Minecraft profile API").clone(), data.get::<DbPool>().expect("missing database connection").clone())
+        };
+        let Some(profile) = profile_api.lookup(name).await? else {
+            return Ok(MessageBuilder::default().push("There is no Minecraft account named ").push_mono_safe(name).push('.').build())
+        };
+        if User::from_discord(&pool, discord_id).await?.is_none() {
+            return Ok(format!("You're not in the people database yet. Please ask an admin to add you."))
+        }
+        if let Some(linked) = User::from_minecraft_uuid(&pool, profile.id).await? {
+            return Ok(if linked.discord_id() == Some(discord_id) {
+                MessageBuilder::default().push("The Minecraft account ").push_mono_safe(&profile.name).push(" is already linked to your profile.").build()
+            } else {
+                MessageBuilder::default().push("The Minecraft account ").push_mono_safe(&profile.name).push(" is already linked to someone else's profile.").build()
+            })
+        }
+        profile
+    };
+    let code = format!("{:06}", rng().random_range(0..1_000_000));
+    let reply = MessageBuilder::default()
+        .push("To confirm that you own the Minecraft account ")
+        .push_mono_safe(&profile.name)
+        .push(", join any of our running worlds and send ")
+        .push_mono(&code)
+        .push(format!(" in chat within {} minutes.", CODE_VALIDITY.as_secs() / 60))
+        .build();
+    ctx.data.write().await.get_mut::<PendingLinks>().expect("missing pending Minecraft links").insert(profile.name.to_lowercase(), PendingLink {
+        discord_id,
+        profile,
+        code,
+        expires: Instant::now() + CODE_VALIDITY,
+    });
+    Ok(reply)
+}
+
+/// The website page where a signed-in user can link their Twitch account.
+pub(crate) fn twitch_url() -> String {
+    uri!(base_uri(), crate::auth::twitch_login(_)).to_string()
+}
+
+/// Checks whether a Minecraft chat message confirms a pending account link, and if so, saves the link.
+///
+/// Returns `true` if the message was a link code, in which case it shouldn't be relayed to Discord.
+///
+/// If the player's UUID isn't known from the log, e.g. because they joined before the bot was restarted, it's looked up using `list uuids`.
+pub(crate) async fn check_code(ctx: &Context, world: &systemd_minecraft::World, player: &str, player_uuid: Option<Uuid>, msg: &str) -> Result<bool, Error> {
+    let key = player.to_lowercase();
+    if !ctx.data.read().await.get::<PendingLinks>().expect("missing pending Minecraft links").contains_key(&key) { return Ok(false) } // avoid locking for writing on every chat message
+    let player_uuid = match player_uuid {
+        Some(player_uuid) => Some(player_uuid),
+        None => online_players(world).await?.and_then(|players| players.into_iter().find_map(|(name, uuid)| (name == player).then_some(uuid))),
+    };
+    let link = {
+        let mut data = ctx.data.write().await;
+        let pending_links = data.get_mut::<PendingLinks>().expect("missing pending Minecraft links");
+        let now = Instant::now();
+        pending_links.retain(|_, link| now < link.expires);
+        if !pending_links.get(&key).is_some_and(|link| link.is_confirmed_by(player_uuid, msg, now)) { return Ok(false) }
+        pending_links.remove(&key).expect("checked above")
+    };
+    let data = ctx.data.read().await;
+    let pool = data.get::<DbPool>().expect("missing database connection");
+    let mut user = User::from_discord(pool, link.discord_id).await?.ok_or(Error::UnknownUser)?;
+    user.set_minecraft(link.profile.id, link.profile.name.clone());
+    user.save_data(pool).await?;
+    link.discord_id.direct_message(ctx, CreateMessage::new().content(MessageBuilder::default()
+        .push("The Minecraft account ")
+        .push_mono_safe(&link.profile.name)
+        .push(" is now linked to your Wurstmineberg profile.")
+        .build()
+    )).await?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile() -> Profile {
+        Profile {
+            id: Uuid::from_u128(0xc2e8b1f4_3a7d_4e59_9b21_6f0d8a4c7e13),
+            name: format!("Fenhl"),
+        }
+    }
+
+    #[test]
+    fn lookup() {
+        let api = ProfileApi::Fixed(vec![profile()]);
+        assert_eq!(futures::executor::block_on(api.lookup("fenhl")).expect("lookup failed").map(|profile| profile.id), Some(profile().id));
+        assert!(futures::executor::block_on(api.lookup("Notch")).expect("lookup failed").is_none());
+    }
+
+    #[test]
+    fn confirmation() {
+        let now = Instant::now();
+        let link = PendingLink {
+            discord_id: UserId::new(86841168427495424),
+            profile: profile(),
+            code: format!("012345"),
+            expires: now + CODE_VALIDITY,
+        };
+        assert!(link.is_confirmed_by(Some(profile().id), "012345", now));
+        assert!(link.is_confirmed_by(Some(profile().id), " 012345 ", now));
+        assert!(!link.is_confirmed_by(Some(profile().id), "12345", now));
+        assert!(!link.is_confirmed_by(Some(Uuid::nil()), "012345", now)); // someone else using the same name, e.g. in offline mode
+        assert!(!link.is_confirmed_by(None, "012345", now)); // UUID not logged yet
+        assert!(!link.is_confirmed_by(Some(profile().id), "012345", now + CODE_VALIDITY));
+    }
+}
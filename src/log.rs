@@ -11,8 +11,8 @@ use {
         time::Duration,
     },
     chase::Chaser,
+    chrono::prelude::*,
     futures::{
-        future::try_join_all,
         pin_mut,
         prelude::*,
         stream::{
@@ -22,20 +22,30 @@ use {
     },
     itertools::Itertools as _,
     lazy_regex::{
+        regex,
         regex_captures,
+        regex_is_match,
         regex_replace_all,
     },
+    minecraft::chat::Chat,
     regex::Regex,
     serde::Deserialize,
     serenity::{
         all::{
+            Colour,
+            CreateAllowedMentions,
+            CreateEmbed,
+            CreateEmbedAuthor,
+            CreateMessage,
             EditChannel,
             ExecuteWebhook,
+            UserId,
         },
         prelude::*,
         utils::MessageBuilder,
     },
     serenity_utils::RwFuture,
+    sqlx::PgPool,
     tokio::{
         io::{
             self,
@@ -44,6 +54,11 @@ use {
             BufReader,
         },
         sync::RwLock,
+        task::JoinHandle,
+        time::{
+            Instant,
+            sleep,
+        },
     },
     tokio_stream::wrappers::{
         LinesStream,
@@ -51,6 +66,7 @@ use {
     },
     tokio_util::io::StreamReader,
     url::Url,
+    uuid::Uuid,
     wheel::{
         fs::{
             self,
@@ -62,19 +78,35 @@ use {
             ReqwestResponseExt as _,
         },
     },
-    crate::BASE_PATH,
+    crate::{
+        BASE_PATH,
+        config::LogEvent,
+        discord::{
+            DbPool,
+            main_guild,
+        },
+        http::base_uri,
+        user::User,
+    },
 };
 #[cfg(not(target_os = "linux"))] use crate::systemd_minecraft;
 
+/// Minimum time between two “server is lagging” alerts for the same world.
+const LAG_ALERT_INTERVAL: Duration = Duration::from_mins(30);
+/// Minimum time between two admin notifications about the same non-whitelisted player.
+const WHITELIST_ALERT_INTERVAL: Duration = Duration::from_hours(1);
+
 #[derive(Debug, thiserror::Error)]
 pub(crate) enum Error {
     #[error(transparent)] Chase(#[from] chase::Error),
     #[error(transparent)] Io(#[from] io::Error),
     #[error(transparent)] Json(#[from] serde_json::Error),
+    #[error(transparent)] Link(#[from] crate::link::Error),
     #[error(transparent)] Minecraft(#[from] systemd_minecraft::Error),
     #[error(transparent)] Regex(#[from] regex::Error),
     #[error(transparent)] Reqwest(#[from] reqwest::Error),
     #[error(transparent)] Serenity(#[from] serenity::Error),
+    #[error(transparent)] Sql(#[from] sqlx::Error),
     #[error(transparent)] Task(#[from] tokio::task::JoinError),
     #[error(transparent)] Wheel(#[from] wheel::Error),
     #[error(transparent)] Zip(#[from] async_zip::error::ZipError),
@@ -84,8 +116,6 @@ pub(crate) enum Error {
     MissingLangFile,
     #[error("Minecraft version not found in launcher manifest")]
     MissingVersion,
-    #[error("failed to start log handler: no worlds configured")]
-    NoWorlds, //TODO remove once `handle` automatically handles new worlds as they are created
 }
 
 impl From<Never> for Error {
@@ -94,12 +124,37 @@ impl From<Never> for Error {
     }
 }
 
+#[derive(Debug, PartialEq)]
 enum AdvancementKind {
     Challenge,
     Goal,
     Task,
 }
 
+/// Who sent a `/say` or non-player `/me` message.
+#[derive(Debug, PartialEq)]
+enum SaySource {
+    /// The server console.
+    Server,
+    /// An RCON client.
+    Rcon,
+    /// An unnamed command block, called `@`. Named command blocks can't be told apart from players or other bracketed output, so they're treated as players if their name is a valid player name and not relayed otherwise.
+    CommandBlock(String),
+    /// A player using `/say`.
+    Player(String),
+}
+
+impl SaySource {
+    fn name(&self) -> &str {
+        match self {
+            Self::Server => "Server",
+            Self::Rcon => "Rcon",
+            Self::CommandBlock(name) | Self::Player(name) => name,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
 enum RegularLine {
     ServerStart {
         minecraft_version: String,
@@ -113,17 +168,126 @@ enum RegularLine {
         kind: AdvancementKind,
         player: String,
         advancement: String,
+        description: Option<String>,
     },
     Death {
         msg: String,
     },
+    /// A message from `/say`, or from `/me` run by the console or an RCON client.
+    Say {
+        source: SaySource,
+        msg: String,
+        is_action: bool,
+    },
+    ServerStop,
+    /// The server watchdog detected a hung tick and is forcibly shutting down the server.
+    ServerCrash,
+    Lag {
+        ms: u64,
+        ticks: u64,
+    },
+    WhitelistRejection {
+        player: String,
+        uuid: Option<Uuid>,
+    },
+    /// Logged by the authentication thread when a player joins.
+    PlayerUuid {
+        player: String,
+        uuid: Uuid,
+    },
     Unknown,
 }
 
+/// Where a log follower gets the language data needed to recognize death messages and look up advancement descriptions.
+enum LangSource {
+    /// Download the client jar of the running Minecraft version and read its English language file.
+    ClientJar(reqwest::Client),
+    /// The language data was set up front and does not change with the Minecraft version.
+    Fixed,
+}
+
 struct FollowerState {
-    http_client: reqwest::Client,
+    lang_source: LangSource,
     minecraft_version: Option<String>,
     death_messages: HashMap<String, Regex>,
+    /// Maps advancement titles, as they appear in the log, to their descriptions.
+    advancement_descriptions: HashMap<String, String>,
+}
+
+impl FollowerState {
+    fn set_lang(&mut self, lang: HashMap<String, String>) -> Result<(), Error> {
+        self.death_messages = lang.iter()
+            .filter(|(key, _)| key.starts_with("death."))
+            .map(|(key, format)| Ok::<_, Error>((key.to_owned(), format_to_regex(format)?)))
+            .try_collect()?;
+        self.advancement_descriptions = lang.iter()
+            .filter_map(|(key, title)| {
+                let key = key.strip_prefix("advancements.")?.strip_suffix(".title")?;
+                Some((title.clone(), lang.get(&format!("advancements.{key}.description"))?.clone()))
+            })
+            .collect();
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct VersionManifestLatest {
+    pub(crate) release: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct VersionManifestInfo {
+    pub(crate) id: String,
+    url: Url,
+}
+
+/// The list of Minecraft versions known to the launcher.
+#[derive(Deserialize)]
+pub(crate) struct VersionManifest {
+    pub(crate) latest: VersionManifestLatest,
+    pub(crate) versions: Vec<VersionManifestInfo>,
+}
+
+pub(crate) async fn version_manifest(http_client: &reqwest::Client) -> Result<VersionManifest, wheel::Error> {
+    Ok(http_client.get("https://launchermeta.mojang.com/mc/game/version_manifest.json")
+        .send().await?
+        .detailed_error_for_status().await?
+        .json_with_text_in_error::<VersionManifest>().await?)
+}
+
+/// Returns the English language data for the given Minecraft version, downloading the client jar if necessary.
+async fn load_lang(http_client: &reqwest::Client, version: &str) -> Result<HashMap<String, String>, Error> {
+    let client_jar_dir = Path::new(BASE_PATH).join("home").join(".minecraft-wurstmineberg").join("versions").join(version);
+    let client_jar_path = client_jar_dir.join(format!("{version}.jar"));
+    if !fs::exists(&client_jar_path).await? {
+        #[derive(Deserialize)]
+        struct VersionInfo {
+            downloads: VersionInfoDownloads,
+        }
+
+        #[derive(Deserialize)]
+        struct VersionInfoDownloads {
+            client: VersionInfoDownload,
+        }
+
+        #[derive(Deserialize)]
+        struct VersionInfoDownload {
+            url: Url,
+        }
+
+        fs::create_dir_all(&client_jar_dir).await?;
+        let version_manifest = version_manifest(http_client).await?;
+        let version_info = http_client.get(version_manifest.versions.into_iter().find(|iter_version| iter_version.id == version).ok_or(Error::MissingVersion)?.url)
+            .send().await?
+            .detailed_error_for_status().await?
+            .json_with_text_in_error::<VersionInfo>().await?;
+        io::copy_buf(&mut StreamReader::new(http_client.get(version_info.downloads.client.url).send().await?.detailed_error_for_status().await?.bytes_stream().map_err(io_error_from_reqwest)), &mut File::create(&client_jar_path).await?).await?;
+    }
+    let zip_file = async_zip::tokio::read::fs::ZipFileReader::new(client_jar_path).await?;
+    let index = zip_file.file().entries().iter().position(|entry| entry.filename().as_str().map_or(false, |filename| filename == "assets/minecraft/lang/en_us.json")).ok_or(Error::MissingLangFile)?;
+    let mut english = String::default();
+    zip_file.reader_with_entry(index).await?.read_to_string_checked(&mut english).await?;
+    Ok(serde_json::from_str(&english)?)
 }
 
 fn format_to_regex(format: &str) -> Result<Regex, regex::Error> {
@@ -136,55 +300,10 @@ impl RegularLine {
             let mut state = state.write().await;
             if state.minecraft_version.as_ref().map_or(true, |prev_version| prev_version != version) {
                 state.minecraft_version = Some(version.to_owned());
-                let client_jar_dir = Path::new(BASE_PATH).join("home").join(".minecraft-wurstmineberg").join("versions").join(version);
-                let client_jar_path = client_jar_dir.join(format!("{version}.jar"));
-                if !fs::exists(&client_jar_path).await? {
-                    #[derive(Deserialize)]
-                    struct VersionManifestInfo {
-                        id: String,
-                        url: Url,
-                    }
-
-                    #[derive(Deserialize)]
-                    struct VersionManifest {
-                        versions: Vec<VersionManifestInfo>,
-                    }
-
-                    #[derive(Deserialize)]
-                    struct VersionInfo {
-                        downloads: VersionInfoDownloads,
-                    }
-
-                    #[derive(Deserialize)]
-                    struct VersionInfoDownloads {
-                        client: VersionInfoDownload,
-                    }
-
-                    #[derive(Deserialize)]
-                    struct VersionInfoDownload {
-                        url: Url,
-                    }
-
-                    fs::create_dir_all(&client_jar_dir).await?;
-                    let version_manifest = state.http_client.get("https://launchermeta.mojang.com/mc/game/version_manifest.json")
-                        .send().await?
-                        .detailed_error_for_status().await?
-                        .json_with_text_in_error::<VersionManifest>().await?;
-                    let version_info = state.http_client.get(version_manifest.versions.into_iter().find(|iter_version| iter_version.id == version).ok_or(Error::MissingVersion)?.url)
-                        .send().await?
-                        .detailed_error_for_status().await?
-                        .json_with_text_in_error::<VersionInfo>().await?;
-                    io::copy_buf(&mut StreamReader::new(state.http_client.get(version_info.downloads.client.url).send().await?.detailed_error_for_status().await?.bytes_stream().map_err(io_error_from_reqwest)), &mut File::create(&client_jar_path).await?).await?;
+                if let LangSource::ClientJar(ref http_client) = state.lang_source {
+                    let lang = load_lang(http_client, version).await?;
+                    state.set_lang(lang)?;
                 }
-                let zip_file = async_zip::tokio::read::fs::ZipFileReader::new(client_jar_path).await?;
-                let index = zip_file.file().entries().iter().position(|entry| entry.filename().as_str().map_or(false, |filename| filename == "assets/minecraft/lang/en_us.json")).ok_or(Error::MissingLangFile)?;
-                let mut english = String::default();
-                zip_file.reader_with_entry(index).await?.read_to_string_checked(&mut english).await?;
-                state.death_messages = serde_json::from_str::<HashMap<String, String>>(&english)?
-                    .into_iter()
-                    .filter(|(key, _)| key.starts_with("death."))
-                    .map(|(key, format)| Ok::<_, Error>((key.to_owned(), format_to_regex(&format)?)))
-                    .try_collect()?;
             }
             Self::ServerStart {
                 minecraft_version: version.to_owned(),
@@ -196,39 +315,91 @@ impl RegularLine {
                 is_action: false,
             }
         } else if let Some((_, sender, msg)) = regex_captures!("^(?:\\[Not Secure\\] )?\\* ([A-Za-z0-9_]{3,16}) (.+)$", s) {
-            Self::Chat {
-                sender: sender.to_owned(),
+            match sender {
+                "Server" => Self::Say {
+                    source: SaySource::Server,
+                    msg: msg.to_owned(),
+                    is_action: true,
+                },
+                "Rcon" => Self::Say {
+                    source: SaySource::Rcon,
+                    msg: msg.to_owned(),
+                    is_action: true,
+                },
+                _ => Self::Chat {
+                    sender: sender.to_owned(),
+                    msg: msg.to_owned(),
+                    is_action: true,
+                },
+            }
+        } else if let Some((_, source, msg)) = regex_captures!("^(?:\\[Not Secure\\] )?\\[(@|[A-Za-z0-9_]{3,16})\\] (.+)$", s) {
+            // only the sources `/say` can have are matched, so other bracketed output like command feedback (`[Rcon: Added …]`) or plugin prefixes with spaces isn't relayed
+            Self::Say {
+                source: match source {
+                    "Server" => SaySource::Server,
+                    "Rcon" => SaySource::Rcon,
+                    "@" => SaySource::CommandBlock(source.to_owned()),
+                    _ => SaySource::Player(source.to_owned()),
+                },
                 msg: msg.to_owned(),
-                is_action: true,
+                is_action: false,
             }
         } else if let Some((_, player, advancement)) = regex_captures!(r"^([A-Za-z0-9_]{3,16}) has completed the challenge \[(.+)\]$", s) {
             Self::Advancement {
                 kind: AdvancementKind::Challenge,
                 player: player.to_owned(),
                 advancement: advancement.to_owned(),
+                description: state.read().await.advancement_descriptions.get(advancement).cloned(),
             }
         } else if let Some((_, player, advancement)) = regex_captures!(r"^([A-Za-z0-9_]{3,16}) has reached the goal \[(.+)\]$", s) {
             Self::Advancement {
                 kind: AdvancementKind::Goal,
                 player: player.to_owned(),
                 advancement: advancement.to_owned(),
+                description: state.read().await.advancement_descriptions.get(advancement).cloned(),
             }
         } else if let Some((_, player, advancement)) = regex_captures!(r"^([A-Za-z0-9_]{3,16}) has made the advancement \[(.+)\]$", s) {
             Self::Advancement {
                 kind: AdvancementKind::Task,
                 player: player.to_owned(),
                 advancement: advancement.to_owned(),
+                description: state.read().await.advancement_descriptions.get(advancement).cloned(),
             }
         } else if state.read().await.death_messages.iter().any(|(_, regex)| regex.is_match(s)) {
             Self::Death {
                 msg: s.to_owned(),
             }
+        } else if s == "Stopping server" {
+            Self::ServerStop
+        } else if s == "Considering it to be crashed, server will forcibly shutdown." {
+            Self::ServerCrash
+        } else if let Some((_, ms, ticks)) = regex_captures!(r"^Can't keep up! Is the server overloaded\? Running ([0-9]+)ms or ([0-9]+) ticks behind$", s) {
+            Self::Lag {
+                ms: ms.parse().expect("matched by regex"),
+                ticks: ticks.parse().expect("matched by regex"),
+            }
+        } else if let Some((_, ms, ticks)) = regex_captures!(r"^Can't keep up! Did the system time change, or is the server overloaded\? Running ([0-9]+)ms behind, skipping ([0-9]+) tick\(s\)$", s) {
+            Self::Lag {
+                ms: ms.parse().expect("matched by regex"),
+                ticks: ticks.parse().expect("matched by regex"),
+            }
+        } else if let Some((_, uuid, profile_name, name)) = regex_captures!(r"^(?:Disconnecting )?(?:com\.mojang\.authlib\.GameProfile@[0-9a-f]+\[id=([0-9a-f-]+|null),name=([A-Za-z0-9_]{1,16}),.*\]|([A-Za-z0-9_]{1,16})) \(/[^)]+\)(?: lost connection)?: You are not white-listed on this server!$", s) {
+            Self::WhitelistRejection {
+                player: if profile_name.is_empty() { name } else { profile_name }.to_owned(),
+                uuid: uuid.parse().ok(),
+            }
+        } else if let Some((_, player, uuid)) = regex_captures!("^UUID of player ([A-Za-z0-9_]{3,16}) is ([0-9a-f-]{36})$", s) && let Ok(uuid) = uuid.parse() {
+            Self::PlayerUuid {
+                player: player.to_owned(),
+                uuid,
+            }
         } else {
             Self::Unknown
         })
     }
 }
 
+#[derive(Debug, PartialEq)]
 enum Line {
     Regular {
         content: RegularLine,
@@ -238,7 +409,7 @@ enum Line {
 
 impl Line {
     async fn parse(state: Arc<RwLock<FollowerState>>, s: &str) -> Result<Self, Error> {
-        Ok(if let Some((_, content)) = regex_captures!("^[0-9]+-[0-9]{2}-[0-9]{2} [0-9]{2}:[0-9]{2}:[0-9]{2} \\[[^]]+/(?:INFO|WARN|ERROR)\\]: (.+)$", s) {
+        Ok(if let Some((_, content)) = regex_captures!("^[0-9]+-[0-9]{2}-[0-9]{2} [0-9]{2}:[0-9]{2}:[0-9]{2} \\[[^]]+/(?:INFO|WARN|ERROR|FATAL)\\]: (.+)$", s) {
             Self::Regular {
                 content: RegularLine::parse(state, content).await?,
             }
@@ -256,10 +427,9 @@ async fn history_paths(world: &systemd_minecraft::World) -> Result<Vec<PathBuf>,
     Ok(logs)
 }
 
-fn history(http_client: reqwest::Client, world: &systemd_minecraft::World) -> impl Stream<Item = Result<Line, Error>> + '_ {
+fn history(world: &systemd_minecraft::World) -> impl Stream<Item = Result<Line, Error>> + '_ {
     stream::once(history_paths(world))
         .and_then(move |paths| {
-            let http_client = http_client.clone();
             future::ok(
                 stream::iter(paths)
                     .then(|path| async move {
@@ -273,15 +443,13 @@ fn history(http_client: reqwest::Client, world: &systemd_minecraft::World) -> im
                     })
                     .and_then(|contents| future::ok(stream::iter(contents.lines().rev().map(|line| line.to_owned()).collect_vec()).map(Ok)))
                     .try_flatten()
-                    .and_then(move |line| {
-                        let http_client = http_client.clone();
-                        async move {
-                            Line::parse(Arc::new(RwLock::new(FollowerState { // reset state for each line since we're going backwards
-                                minecraft_version: None,
-                                death_messages: HashMap::default(),
-                                http_client,
-                            })), &line).await
-                        }
+                    .and_then(move |line| async move {
+                        Line::parse(Arc::new(RwLock::new(FollowerState { // reset state for each line since we're going backwards
+                            lang_source: LangSource::Fixed, // language data isn't needed to find server starts, so don't download a client jar for each past version
+                            minecraft_version: None,
+                            death_messages: HashMap::default(),
+                            advancement_descriptions: HashMap::default(),
+                        })), &line).await
                     })
                     //TODO chain previous logs
             )
@@ -292,20 +460,32 @@ fn history(http_client: reqwest::Client, world: &systemd_minecraft::World) -> im
 /// Follows the log of the given world, starting after the last line break at the time the stream is started.
 fn follow(http_client: reqwest::Client, world: &systemd_minecraft::World) -> impl Stream<Item = Result<Line, Error>> + '_ {
     let log_path = world.dir().join("logs/latest.log");
-    stream::once(async {
+    stream::once(async move {
         let init_lines = LinesStream::new(BufReader::new(File::open(&log_path).await?).lines()).try_fold(0, |acc, _| future::ok(acc + 1)).await?;
         let chaser = Chaser::new(log_path, chase::Line(init_lines));
+        let mut state = FollowerState {
+            minecraft_version: pin!(history(world).try_filter_map(|line| future::ok(if let Line::Regular { content: RegularLine::ServerStart { minecraft_version } } = line {
+                Some(minecraft_version)
+            } else {
+                None
+            }))).try_next().await?,
+            death_messages: HashMap::default(),
+            advancement_descriptions: HashMap::default(),
+            lang_source: LangSource::ClientJar(http_client.clone()),
+        };
+        if let Some(version) = state.minecraft_version.clone() {
+            match load_lang(&http_client, &version).await {
+                Ok(lang) => state.set_lang(lang)?,
+                Err(e) => {
+                    // keep following without localized messages, and try again on the next server start
+                    eprintln!("failed to load Minecraft {version} language data for {world}: {e} ({e:?})");
+                    state.minecraft_version = None;
+                }
+            }
+        }
         let stream = ReceiverStream::new(chaser.run())
             .scan(
-                Arc::new(RwLock::new(FollowerState {
-                    minecraft_version: pin!(history(http_client.clone(), world).try_filter_map(|line| future::ok(if let Line::Regular { content: RegularLine::ServerStart { minecraft_version } } = line {
-                        Some(minecraft_version)
-                    } else {
-                        None
-                    }))).try_next().await?,
-                    death_messages: HashMap::default(),
-                    http_client,
-                })),
+                Arc::new(RwLock::new(state)),
                 |state, res| {
                     let state = Arc::clone(&state);
                     async move {
@@ -320,7 +500,33 @@ fn follow(http_client: reqwest::Client, world: &systemd_minecraft::World) -> imp
     }).try_flatten()
 }
 
-pub(crate) async fn handle(ctx_fut: RwFuture<Context>) -> Result<Never, Error> { //TODO dynamically update handled worlds as they are added/removed
+enum FollowerStatus {
+    Running(JoinHandle<Result<Never, Error>>),
+    Restarting {
+        at: Instant,
+    },
+}
+
+struct Follower {
+    status: FollowerStatus,
+    last_crash: Instant,
+    wait_time: Duration,
+}
+
+impl Drop for Follower {
+    fn drop(&mut self) {
+        // a dropped JoinHandle detaches the task, so followers would otherwise keep running unsupervised
+        if let FollowerStatus::Running(ref handle) = self.status {
+            handle.abort();
+        }
+    }
+}
+
+/// Follows the logs of all worlds, starting and stopping followers as worlds are added or removed.
+///
+/// A follower that errors is restarted with exponential backoff without affecting the followers for other worlds.
+/// All followers are stopped when this returns.
+pub(crate) async fn handle<F: Future<Output = ()>>(ctx_fut: RwFuture<Context>, notify_thread_crash: impl Fn(String, Box<dyn std::error::Error + Send + Sync>, Option<Duration>) -> F) -> Result<Never, Error> {
     let http_client = reqwest::Client::builder()
         .user_agent(concat!("wurstminebot/", env!("CARGO_PKG_VERSION")))
         .timeout(Duration::from_secs(30))
@@ -328,75 +534,265 @@ pub(crate) async fn handle(ctx_fut: RwFuture<Context>) -> Result<Never, Error> {
         .hickory_dns(true)
         .https_only(true)
         .build()?;
-    let mut handles = Vec::default();
-    for world in systemd_minecraft::World::all().await? {
-        handles.push(tokio::spawn(handle_world(http_client.clone(), ctx_fut.clone(), world)));
+    let mut followers = HashMap::<systemd_minecraft::World, Follower>::default();
+    loop {
+        let worlds = match systemd_minecraft::World::all().await {
+            Ok(worlds) => worlds,
+            Err(e) => {
+                // keep supervising the existing followers
+                eprintln!("failed to list worlds for log followers: {e} ({e:?})");
+                notify_thread_crash(format!("log follower supervisor"), Box::new(e), Some(Duration::from_secs(5))).await;
+                sleep(Duration::from_secs(5)).await;
+                continue
+            }
+        };
+        followers.retain(|world, _| worlds.contains(world)); // dropping a follower stops it
+        for world in worlds {
+            followers.entry(world.clone()).or_insert_with(|| Follower {
+                status: FollowerStatus::Running(tokio::spawn(handle_world(http_client.clone(), ctx_fut.clone(), world))),
+                last_crash: Instant::now(),
+                wait_time: Duration::from_secs(1),
+            });
+        }
+        for (world, follower) in &mut followers {
+            match follower.status {
+                FollowerStatus::Running(ref mut handle) => if handle.is_finished() {
+                    let e = match handle.await {
+                        Ok(Ok(never)) => match never {},
+                        Ok(Err(e)) => e,
+                        Err(e) => Error::from(e),
+                    };
+                    if follower.last_crash.elapsed() >= Duration::from_hours(24) {
+                        follower.wait_time = Duration::from_secs(1); // reset wait time after no crash for a day
+                    } else {
+                        follower.wait_time *= 2; // exponential backoff
+                    }
+                    eprintln!("log follower for {world} crashed: {e} ({e:?})");
+                    if follower.wait_time >= Duration::from_secs(2) { // only notify on multiple consecutive errors
+                        notify_thread_crash(format!("log follower for {world}"), Box::new(e), Some(follower.wait_time)).await;
+                    }
+                    follower.last_crash = Instant::now();
+                    follower.status = FollowerStatus::Restarting { at: follower.last_crash + follower.wait_time };
+                },
+                FollowerStatus::Restarting { at } => if at <= Instant::now() {
+                    follower.status = FollowerStatus::Running(tokio::spawn(handle_world(http_client.clone(), ctx_fut.clone(), world.clone())));
+                },
+            }
+        }
+        sleep(Duration::from_secs(5)).await;
     }
-    match try_join_all(handles).await?.pop() {
-        Some(Ok(never)) => match never {},
-        Some(Err(e)) => Err(e),
-        None => Err(Error::NoWorlds),
+}
+
+/// Converts a Minecraft chat message to Discord markdown, turning `@name` into a Discord mention if `name` is the Wurstmineberg ID or Minecraft nickname of a person with a linked Discord account.
+///
+/// Returns the Discord users who were mentioned, so the message can be sent with only those mentions allowed.
+async fn minecraft_to_discord(db_pool: &PgPool, msg: &str, builder: &mut MessageBuilder) -> sqlx::Result<Vec<UserId>> {
+    let mut mentions = Vec::default();
+    let mut last_end = 0;
+    for captures in regex!("(?:^|[^A-Za-z0-9_])@([A-Za-z0-9_]{1,16})").captures_iter(msg) {
+        let name = captures.get(1).expect("group is not optional");
+        let user = if let Some(user) = User::from_wmbid(db_pool, name.as_str()).await? {
+            Some(user)
+        } else {
+            User::from_minecraft_nick(db_pool, name.as_str()).await?
+        };
+        if let Some(discord_id) = user.and_then(|user| user.discord_id()) {
+            let mention_start = name.start() - 1; // include the @
+            builder.push_safe(&msg[last_end..mention_start]);
+            builder.mention(&discord_id);
+            if !mentions.contains(&discord_id) {
+                mentions.push(discord_id);
+            }
+            last_end = name.end();
+        }
     }
+    builder.push_safe(&msg[last_end..]);
+    Ok(mentions)
+}
+
+/// Finds the Wurstmineberg person with the given Minecraft nickname, preferring the UUID from the player's most recent login if known.
+async fn resolve_player(db_pool: &PgPool, player_uuids: &HashMap<String, Uuid>, player: &str) -> sqlx::Result<Option<User>> {
+    if let Some(&uuid) = player_uuids.get(player) {
+        if let Some(user) = User::from_minecraft_uuid(db_pool, uuid).await? {
+            return Ok(Some(user))
+        }
+    }
+    User::from_minecraft_nick(db_pool, player).await
 }
 
 async fn handle_world(http_client: reqwest::Client, ctx_fut: RwFuture<Context>, world: systemd_minecraft::World) -> Result<Never, Error> {
+    let mut started_at = None::<DateTime<Utc>>;
+    let mut stopped_at = None::<DateTime<Utc>>;
+    let mut last_lag_alert = None::<Instant>;
+    let mut last_whitelist_alerts = HashMap::<String, Instant>::default();
+    let mut player_uuids = HashMap::<String, Uuid>::default();
     let follower = follow(http_client, &world);
     pin_mut!(follower);
     while let Some(line) = follower.try_next().await? {
         match line {
             Line::Regular { content, .. } => match content {
                 RegularLine::ServerStart { minecraft_version } => {
+                    let now = Utc::now();
                     let ctx = ctx_fut.read().await;
                     let ctx_data = (*ctx).data.read().await;
                     let config = ctx_data.get::<crate::config::Config>().expect("missing config");
-                    if let Some(chan_id) = config.wurstminebot.world_channels.get(&world) {
-                        if let Some(topic) = config.wurstminebot.world_channel_topics.get(&world) {
+                    if let Some(topic) = config.wurstminebot.world_channel_topics.get(&world) {
+                        for chan_id in config.wurstminebot.guilds().filter_map(|guild| guild.world_channels.get(&world)) {
                             chan_id.edit(&*ctx, EditChannel::new().topic(format!("{topic}, currently running on {minecraft_version}"))).await?;
                         }
                     }
+                    let mut builder = MessageBuilder::default();
+                    builder.push("Server is starting on version ");
+                    builder.push_safe(&minecraft_version);
+                    if let Some(stopped_at) = stopped_at {
+                        builder.push(format!(" (down since <t:{}:R>)", stopped_at.timestamp()));
+                    }
+                    let msg = builder.build();
+                    for chan_id in config.wurstminebot.log_channels(&world, LogEvent::ServerStart) {
+                        chan_id.say(&*ctx, &msg).await?;
+                    }
+                    started_at = Some(now);
+                    stopped_at = None;
+                }
+                RegularLine::ServerStop => {
+                    let now = Utc::now();
+                    let ctx = ctx_fut.read().await;
+                    let ctx_data = (*ctx).data.read().await;
+                    let msg = if let Some(started_at) = started_at {
+                        format!("Server is stopping (up since <t:{}:R>)", started_at.timestamp())
+                    } else {
+                        format!("Server is stopping")
+                    };
+                    for chan_id in ctx_data.get::<crate::config::Config>().expect("missing config").wurstminebot.log_channels(&world, LogEvent::ServerStop) {
+                        chan_id.say(&*ctx, &msg).await?;
+                    }
+                    started_at = None;
+                    stopped_at = Some(now);
+                }
+                RegularLine::ServerCrash => {
+                    let now = Utc::now();
+                    let ctx = ctx_fut.read().await;
+                    let ctx_data = (*ctx).data.read().await;
+                    for chan_id in ctx_data.get::<crate::config::Config>().expect("missing config").wurstminebot.log_channels(&world, LogEvent::ServerCrash) {
+                        chan_id.say(&*ctx, "Server crashed: the watchdog detected a hung server tick and is forcibly shutting down the server").await?;
+                    }
+                    started_at = None;
+                    stopped_at = Some(now);
+                }
+                RegularLine::Lag { ms, ticks } => if last_lag_alert.is_none_or(|last_lag_alert| last_lag_alert.elapsed() >= LAG_ALERT_INTERVAL) {
+                    let ctx = ctx_fut.read().await;
+                    let ctx_data = (*ctx).data.read().await;
+                    for chan_id in ctx_data.get::<crate::config::Config>().expect("missing config").wurstminebot.log_channels(&world, LogEvent::Lag) {
+                        chan_id.say(&*ctx, format!("Server is lagging: running {ms}ms or {ticks} ticks behind")).await?;
+                    }
+                    last_lag_alert = Some(Instant::now());
+                },
+                RegularLine::WhitelistRejection { player, uuid } => if last_whitelist_alerts.get(&player).is_none_or(|last_alert| last_alert.elapsed() >= WHITELIST_ALERT_INTERVAL) {
+                    let ctx = ctx_fut.read().await;
+                    let guild = main_guild(&*ctx).await;
+                    let mut builder = MessageBuilder::default();
+                    builder.role(guild.admin_role);
+                    builder.push(" non-whitelisted player ");
+                    builder.push_mono_safe(&player);
+                    if let Some(uuid) = uuid {
+                        builder.push(" (");
+                        builder.push_mono(uuid.to_string());
+                        builder.push(')');
+                    }
+                    builder.push(" tried to connect to ");
+                    builder.push_safe(world.to_string());
+                    guild.dev_channel.say(&*ctx, builder.build()).await?;
+                    last_whitelist_alerts.insert(player, Instant::now());
+                },
+                RegularLine::PlayerUuid { player, uuid } => {
+                    player_uuids.insert(player, uuid);
                 }
                 RegularLine::Chat { sender, msg, is_action } => {
                     let ctx = ctx_fut.read().await;
+                    if !is_action && crate::link::check_code(&*ctx, &world, &sender, player_uuids.get(&sender).copied(), &msg).await? {
+                        // don't leak the code to Discord
+                        world.tellraw(&sender, Chat::from(format!("Your Minecraft account is now linked to your Wurstmineberg profile.")).color(minecraft::chat::Color::Aqua)).await?;
+                        continue
+                    }
                     let ctx_data = (*ctx).data.read().await;
-                    if let Some(chan_id) = ctx_data.get::<crate::config::Config>().expect("missing config").wurstminebot.world_channels.get(&world) {
+                    let user = resolve_player(ctx_data.get::<DbPool>().expect("missing database connection"), &player_uuids, &sender).await?;
+                    let display_name = user.as_ref().map_or_else(|| sender.clone(), |user| user.to_string());
+                    let mut builder = MessageBuilder::default();
+                    if is_action {
+                        builder.push('_');
+                    }
+                    let mentions = minecraft_to_discord(ctx_data.get::<DbPool>().expect("missing database connection"), &msg, &mut builder).await?;
+                    if is_action {
+                        builder.push('_');
+                    }
+                    let content = builder.build();
+                    // only ping people who were explicitly mentioned, never roles or @everyone
+                    let allowed_mentions = CreateAllowedMentions::new().users(mentions);
+                    for chan_id in ctx_data.get::<crate::config::Config>().expect("missing config").wurstminebot.log_channels(&world, LogEvent::Chat) {
                         if let Ok(webhook) = chan_id.webhooks(&*ctx).await?.into_iter().exactly_one() {
-                            webhook.execute(&*ctx, false, ExecuteWebhook::new()
-                                .avatar_url(format!("https://minotar.net/armor/bust/{sender}/1024.png"))
-                                .content(if is_action {
-                                    let mut builder = MessageBuilder::default();
-                                    builder.push_italic_safe(msg);
-                                    builder.build()
-                                } else {
-                                    let mut builder = MessageBuilder::default();
-                                    builder.push_safe(msg);
-                                    builder.build()
-                                })
-                                .username(sender) //TODO use Discord nickname instead of Minecraft nickname?
-                            ).await?;
+                            let mut execute = ExecuteWebhook::new()
+                                .allowed_mentions(allowed_mentions.clone())
+                                .content(&content)
+                                .username(&display_name);
+                            // players without an avatar on file get the webhook's default avatar
+                            if let Some(avatar_url) = user.as_ref().and_then(|user| user.avatar_url()) {
+                                execute = execute.avatar_url(avatar_url);
+                            }
+                            webhook.execute(&*ctx, false, execute).await?;
                         }
                     }
                 }
-                RegularLine::Advancement { kind, player, advancement } => {
+                RegularLine::Say { source, msg, is_action } => {
                     let ctx = ctx_fut.read().await;
                     let ctx_data = (*ctx).data.read().await;
-                    if let Some(chan_id) = ctx_data.get::<crate::config::Config>().expect("missing config").wurstminebot.world_channels.get(&world) {
-                        chan_id.say(&*ctx, MessageBuilder::default()
-                            .push_safe(player)
-                            .push(match kind {
-                                AdvancementKind::Challenge => " has completed the challenge [",
-                                AdvancementKind::Goal => " has reached the goal [",
-                                AdvancementKind::Task => " has made the advancement [",
-                            })
-                            .push_safe(advancement)
-                            .push(']')
-                            .build()).await?;
+                    let mut builder = MessageBuilder::default();
+                    if is_action {
+                        builder.push_italic_safe(format!("* {} {msg}", source.name()));
+                    } else {
+                        builder.push_bold_safe(format!("[{}]", source.name()));
+                        builder.push(' ');
+                        builder.push_safe(msg);
+                    }
+                    let content = builder.build();
+                    for chan_id in ctx_data.get::<crate::config::Config>().expect("missing config").wurstminebot.log_channels(&world, LogEvent::Say) {
+                        chan_id.send_message(&*ctx, CreateMessage::new()
+                            .allowed_mentions(CreateAllowedMentions::new())
+                            .content(&content)
+                        ).await?;
+                    }
+                }
+                RegularLine::Advancement { kind, player, advancement, description } => {
+                    let ctx = ctx_fut.read().await;
+                    let ctx_data = (*ctx).data.read().await;
+                    let (event, verb, colour) = match kind {
+                        AdvancementKind::Challenge => (LogEvent::AdvancementChallenge, "has completed the challenge", Colour(0xaa00aa)),
+                        AdvancementKind::Goal => (LogEvent::AdvancementGoal, "has reached the goal", Colour(0x55ffff)),
+                        AdvancementKind::Task => (LogEvent::AdvancementTask, "has made the advancement", Colour(0x55ff55)),
+                    };
+                    let user = resolve_player(ctx_data.get::<DbPool>().expect("missing database connection"), &player_uuids, &player).await?;
+                    let mut author = CreateEmbedAuthor::new(&player);
+                    if let Some(ref user) = user {
+                        if let Some(head_url) = user.player_head_url() {
+                            author = author.icon_url(head_url);
+                        }
+                        author = author.url(format!("{}{}", base_uri(), user.profile_url()));
+                    }
+                    let mut embed = CreateEmbed::new()
+                        .author(author)
+                        .colour(colour)
+                        .title(format!("{verb} [{advancement}]"));
+                    if let Some(description) = description {
+                        embed = embed.description(description);
+                    }
+                    for chan_id in ctx_data.get::<crate::config::Config>().expect("missing config").wurstminebot.log_channels(&world, event) {
+                        chan_id.send_message(&*ctx, CreateMessage::new().embed(embed.clone())).await?;
                     }
                 }
                 RegularLine::Death { msg, .. } => {
                     let ctx = ctx_fut.read().await;
                     let ctx_data = (*ctx).data.read().await;
-                    if let Some(chan_id) = ctx_data.get::<crate::config::Config>().expect("missing config").wurstminebot.world_channels.get(&world) {
-                        chan_id.say(&*ctx, msg).await?;
+                    for chan_id in ctx_data.get::<crate::config::Config>().expect("missing config").wurstminebot.log_channels(&world, LogEvent::Death) {
+                        chan_id.say(&*ctx, &msg).await?;
                     }
                 }
                 RegularLine::Unknown => {} // ignore all other lines for now
@@ -406,3 +802,119 @@ async fn handle_world(http_client: reqwest::Client, ctx_fut: RwFuture<Context>,
     }
     Err(Error::FollowEnded)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Line {
+        let mut state = FollowerState {
+            lang_source: LangSource::Fixed,
+            minecraft_version: None,
+            death_messages: HashMap::default(),
+            advancement_descriptions: HashMap::default(),
+        };
+        state.set_lang(serde_json::from_str(include_str!("../tests/fixtures/en_us.json")).expect("invalid language fixture")).expect("invalid language fixture");
+        futures::executor::block_on(Line::parse(Arc::new(RwLock::new(state)), line)).expect("failed to parse log line")
+    }
+
+    fn regular(content: RegularLine) -> Line {
+        Line::Regular { content }
+    }
+
+    #[test]
+    fn server_lifecycle() {
+        assert_eq!(parse("2024-08-09 18:01:12 [Server thread/INFO]: Starting minecraft server version 1.21.1"), regular(RegularLine::ServerStart { minecraft_version: format!("1.21.1") }));
+        assert_eq!(parse("2024-08-09 18:01:12 [Server thread/INFO]: Starting minecraft server version 24w14a"), regular(RegularLine::ServerStart { minecraft_version: format!("24w14a") }));
+        assert_eq!(parse("2024-08-09 18:01:12 [Server thread/INFO]: Starting minecraft server version 1.7.10"), regular(RegularLine::ServerStart { minecraft_version: format!("1.7.10") }));
+        assert_eq!(parse("2024-08-09 21:30:00 [Server thread/INFO]: Stopping server"), regular(RegularLine::ServerStop));
+        assert_eq!(parse("2024-08-09 21:30:00 [Server Watchdog/FATAL]: Considering it to be crashed, server will forcibly shutdown."), regular(RegularLine::ServerCrash));
+        assert_eq!(parse("2024-08-09 19:12:44 [Server thread/WARN]: Can't keep up! Is the server overloaded? Running 5023ms or 100 ticks behind"), regular(RegularLine::Lag { ms: 5023, ticks: 100 }));
+        assert_eq!(parse("2024-08-09 19:12:44 [Server thread/WARN]: Can't keep up! Did the system time change, or is the server overloaded? Running 2504ms behind, skipping 50 tick(s)"), regular(RegularLine::Lag { ms: 2504, ticks: 50 }));
+    }
+
+    #[test]
+    fn chat() {
+        assert_eq!(parse("2024-08-09 18:05:00 [Server thread/INFO]: <Fenhl> hello"), regular(RegularLine::Chat { sender: format!("Fenhl"), msg: format!("hello"), is_action: false }));
+        assert_eq!(parse("2024-08-09 18:05:00 [Server thread/INFO]: [Not Secure] <Fenhl> hello"), regular(RegularLine::Chat { sender: format!("Fenhl"), msg: format!("hello"), is_action: false }));
+        assert_eq!(parse("2024-08-09 18:05:00 [Server thread/INFO]: * Fenhl waves"), regular(RegularLine::Chat { sender: format!("Fenhl"), msg: format!("waves"), is_action: true }));
+        assert_eq!(parse("2024-08-09 18:05:00 [Server thread/INFO]: [Not Secure] * Fenhl waves"), regular(RegularLine::Chat { sender: format!("Fenhl"), msg: format!("waves"), is_action: true }));
+        // chat messages that look like death messages are still chat
+        assert_eq!(parse("2024-08-09 18:05:00 [Server thread/INFO]: <Fenhl> I was slain by a zombie"), regular(RegularLine::Chat { sender: format!("Fenhl"), msg: format!("I was slain by a zombie"), is_action: false }));
+    }
+
+    #[test]
+    fn say() {
+        assert_eq!(parse("2024-08-09 18:05:00 [Server thread/INFO]: [Server] restarting in 5 minutes"), regular(RegularLine::Say { source: SaySource::Server, msg: format!("restarting in 5 minutes"), is_action: false }));
+        assert_eq!(parse("2024-08-09 18:05:00 [Server thread/INFO]: * Server is restarting"), regular(RegularLine::Say { source: SaySource::Server, msg: format!("is restarting"), is_action: true }));
+        assert_eq!(parse("2024-08-09 18:05:00 [Server thread/INFO]: [Rcon] backup complete"), regular(RegularLine::Say { source: SaySource::Rcon, msg: format!("backup complete"), is_action: false }));
+        assert_eq!(parse("2024-08-09 18:05:00 [Server thread/INFO]: [@] the gate is open"), regular(RegularLine::Say { source: SaySource::CommandBlock(format!("@")), msg: format!("the gate is open"), is_action: false }));
+        assert_eq!(parse("2024-08-09 18:05:00 [Server thread/INFO]: [Not Secure] [Fenhl] hi everyone"), regular(RegularLine::Say { source: SaySource::Player(format!("Fenhl")), msg: format!("hi everyone"), is_action: false }));
+        // command feedback is not relayed
+        assert_eq!(parse("2024-08-09 18:05:00 [Server thread/INFO]: [Rcon: Added Fenhl to the whitelist]"), regular(RegularLine::Unknown));
+        assert_eq!(parse("2024-08-09 18:05:00 [Server thread/INFO]: [Fenhl: Set the time to 1000]"), regular(RegularLine::Unknown));
+        // other bracketed output is not relayed
+        assert_eq!(parse("2024-08-09 18:05:00 [Server thread/INFO]: [Gate Keeper] the gate is open"), regular(RegularLine::Unknown));
+        assert_eq!(parse("2024-08-09 18:05:00 [Server thread/INFO]: [Essentials Chat] loaded 3 channels"), regular(RegularLine::Unknown));
+        assert_eq!(parse("2024-08-09 18:05:00 [Server thread/INFO]: [!] Server is running low on memory"), regular(RegularLine::Unknown));
+        assert_eq!(parse("2024-08-09 18:05:00 [Server thread/INFO]: [Server thread/WARN] Can't keep up!"), regular(RegularLine::Unknown));
+        assert_eq!(parse("2024-08-09 18:05:00 [Server thread/INFO]: [Fenhl]"), regular(RegularLine::Unknown));
+    }
+
+    #[test]
+    fn advancements() {
+        assert_eq!(parse("2024-08-09 18:10:00 [Server thread/INFO]: Fenhl has made the advancement [Stone Age]"), regular(RegularLine::Advancement { kind: AdvancementKind::Task, player: format!("Fenhl"), advancement: format!("Stone Age"), description: Some(format!("Mine Stone with your new Pickaxe")) }));
+        assert_eq!(parse("2024-08-09 18:10:00 [Server thread/INFO]: Fenhl has reached the goal [Free the End]"), regular(RegularLine::Advancement { kind: AdvancementKind::Goal, player: format!("Fenhl"), advancement: format!("Free the End"), description: Some(format!("Good luck")) }));
+        assert_eq!(parse("2024-08-09 18:10:00 [Server thread/INFO]: Fenhl has completed the challenge [Adventuring Time]"), regular(RegularLine::Advancement { kind: AdvancementKind::Challenge, player: format!("Fenhl"), advancement: format!("Adventuring Time"), description: Some(format!("Discover every biome")) }));
+        // advancements from datapacks or newer versions have no description in the language data
+        assert_eq!(parse("2024-08-09 18:10:00 [Server thread/INFO]: Fenhl has made the advancement [Some Datapack Thing]"), regular(RegularLine::Advancement { kind: AdvancementKind::Task, player: format!("Fenhl"), advancement: format!("Some Datapack Thing"), description: None }));
+        // 1.7-era achievements are not parsed
+        assert_eq!(parse("2024-08-09 18:10:00 [Server thread/INFO]: Fenhl has just earned the achievement [Taking Inventory]"), regular(RegularLine::Unknown));
+    }
+
+    #[test]
+    fn deaths() {
+        assert_eq!(parse("2024-08-09 18:15:00 [Server thread/INFO]: Fenhl was slain by Zombie"), regular(RegularLine::Death { msg: format!("Fenhl was slain by Zombie") }));
+        assert_eq!(parse("2024-08-09 18:15:00 [Server thread/INFO]: Fenhl hit the ground too hard"), regular(RegularLine::Death { msg: format!("Fenhl hit the ground too hard") }));
+        assert_eq!(parse("2024-08-09 18:15:00 [Server thread/INFO]: Fenhl was shot by Skeleton using Bow of Doom"), regular(RegularLine::Death { msg: format!("Fenhl was shot by Skeleton using Bow of Doom") }));
+        assert_eq!(parse("2024-08-09 18:15:00 [Server thread/INFO]: Fenhl joined the game"), regular(RegularLine::Unknown));
+    }
+
+    #[test]
+    fn players() {
+        assert_eq!(parse("2024-08-09 18:00:30 [User Authenticator #1/INFO]: UUID of player Fenhl is c2e8b1f4-3a7d-4e59-9b21-6f0d8a4c7e13"), regular(RegularLine::PlayerUuid { player: format!("Fenhl"), uuid: Uuid::from_u128(0xc2e8b1f4_3a7d_4e59_9b21_6f0d8a4c7e13) }));
+        assert_eq!(parse("2024-08-09 18:00:31 [Server thread/INFO]: Disconnecting com.mojang.authlib.GameProfile@1a2b3c4d[id=0f7d3b8e-5c21-4a96-8e4f-2b9d6c1a7e50,name=Griefer,properties={textures=[]},legacy=false] (/203.0.113.5:51234): You are not white-listed on this server!"), regular(RegularLine::WhitelistRejection { player: format!("Griefer"), uuid: Some(Uuid::from_u128(0x0f7d3b8e_5c21_4a96_8e4f_2b9d6c1a7e50)) }));
+        assert_eq!(parse("2024-08-09 18:00:31 [Server thread/INFO]: Griefer (/203.0.113.5:51234) lost connection: You are not white-listed on this server!"), regular(RegularLine::WhitelistRejection { player: format!("Griefer"), uuid: None }));
+    }
+
+    #[test]
+    fn unknown_lines() {
+        // pre-1.7 format without thread names
+        assert_eq!(parse("2013-07-01 12:00:00 [INFO] <Fenhl> hi"), Line::Unknown);
+        // stack traces
+        assert_eq!(parse("java.lang.NullPointerException: Cannot invoke \"Object.hashCode()\" because \"key\" is null"), Line::Unknown);
+        assert_eq!(parse("\tat net.minecraft.server.MinecraftServer.run(SourceFile:123)"), Line::Unknown);
+        assert_eq!(parse("2024-08-09 18:00:00 [Server thread/DEBUG]: <Fenhl> hi"), Line::Unknown);
+        // time-only prefix as printed to the server console, which isn't what the log files contain
+        assert_eq!(parse("[18:00:00] [Server thread/INFO]: <Fenhl> hi"), Line::Unknown);
+    }
+
+    #[test]
+    fn version_excerpts() {
+        // 1.7.10
+        assert_eq!(parse("2014-08-02 20:14:03 [Server thread/INFO]: Starting minecraft server version 1.7.10"), regular(RegularLine::ServerStart { minecraft_version: format!("1.7.10") }));
+        assert_eq!(parse("2014-08-02 20:31:47 [Server thread/INFO]: <Fenhl> anyone got spare iron?"), regular(RegularLine::Chat { sender: format!("Fenhl"), msg: format!("anyone got spare iron?"), is_action: false }));
+        assert_eq!(parse("2014-08-02 20:33:12 [Server thread/INFO]: Fenhl has just earned the achievement [Getting Wood]"), regular(RegularLine::Unknown));
+        assert_eq!(parse("2014-08-02 20:40:55 [Server thread/INFO]: Fenhl fell from a high place"), regular(RegularLine::Death { msg: format!("Fenhl fell from a high place") }));
+        // 1.12.2
+        assert_eq!(parse("2018-01-14 16:02:09 [Server thread/INFO]: Fenhl has made the advancement [We Need to Go Deeper]"), regular(RegularLine::Advancement { kind: AdvancementKind::Task, player: format!("Fenhl"), advancement: format!("We Need to Go Deeper"), description: Some(format!("Build, light and enter a Nether Portal")) }));
+        assert_eq!(parse("2018-01-14 16:05:31 [Server thread/INFO]: Fenhl has completed the challenge [Return to Sender]"), regular(RegularLine::Advancement { kind: AdvancementKind::Challenge, player: format!("Fenhl"), advancement: format!("Return to Sender"), description: Some(format!("Destroy a Ghast with a fireball")) }));
+        // snapshot
+        assert_eq!(parse("2024-04-03 17:00:01 [Server thread/INFO]: Starting minecraft server version 24w14a"), regular(RegularLine::ServerStart { minecraft_version: format!("24w14a") }));
+        assert_eq!(parse("2024-04-03 17:12:40 [Server thread/INFO]: Fenhl tried to swim in lava"), regular(RegularLine::Death { msg: format!("Fenhl tried to swim in lava") }));
+        // 1.21 with chat signing
+        assert_eq!(parse("2024-08-09 18:05:00 [Server thread/INFO]: [Not Secure] <Fenhl> hello"), regular(RegularLine::Chat { sender: format!("Fenhl"), msg: format!("hello"), is_action: false }));
+        assert_eq!(parse("2024-08-09 18:06:13 [Server thread/INFO]: Fenhl was blown up by Creeper"), regular(RegularLine::Death { msg: format!("Fenhl was blown up by Creeper") }));
+        assert_eq!(parse("2024-08-09 18:06:20 [Server thread/INFO]: Fenhl left the game"), regular(RegularLine::Unknown));
+    }
+}
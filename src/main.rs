@@ -13,19 +13,29 @@ use {
 
 mod about;
 mod api;
+mod audit;
 mod auth;
+mod backup;
 mod cal;
 mod config;
 mod discord;
+mod dm;
 mod form;
 mod http;
+mod invite;
 mod lang;
+mod link;
 mod log;
+mod member;
+mod role_menu;
 mod stats;
 #[cfg(not(target_os = "linux"))] mod systemd_minecraft;
 mod time;
 mod twitch;
+mod update;
 mod user;
+mod voice;
+mod whitelist;
 mod wiki;
 
 include!(concat!(env!("OUT_DIR"), "/build_output.rs"));
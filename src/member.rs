@@ -0,0 +1,189 @@
+use {
+    std::fmt,
+    chrono::prelude::*,
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    serenity::{
+        all::{
+            ButtonStyle,
+            CreateActionRow,
+            CreateButton,
+            CreateMessage,
+            MessageBuilder,
+        },
+        model::prelude::*,
+        prelude::*,
+    },
+    sqlx::{
+        PgExecutor,
+        types::Json,
+    },
+    crate::{
+        config::Config,
+        discord::{
+            DbPool,
+            MessageBuilderExt as _,
+            PgSnowflake,
+        },
+        user::{
+            Status,
+            StatusHistoryItem,
+            User,
+        },
+    },
+};
+
+/// Prefix of the custom IDs of the buttons for confirming that someone who left the Discord server is a former member, followed by their Discord user ID.
+const FORMER_CUSTOM_ID_PREFIX: &str = "former:";
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Serenity(#[from] serenity::Error),
+    #[error(transparent)] Sql(#[from] sqlx::Error),
+    #[error(transparent)] Whitelist(#[from] crate::whitelist::Error),
+}
+
+/// A change to someone's membership in the Discord server, as recorded in the `member_history` table.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub(crate) enum MemberEvent {
+    Joined,
+    Left,
+    /// The role name is recorded since roles may be renamed or deleted later.
+    RoleAdded {
+        role: RoleId,
+        name: String,
+    },
+    RoleRemoved {
+        role: RoleId,
+        name: String,
+    },
+}
+
+impl fmt::Display for MemberEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Joined => write!(f, "joined the Discord server"),
+            Self::Left => write!(f, "left the Discord server"),
+            Self::RoleAdded { name, .. } => write!(f, "got the {name} role"),
+            Self::RoleRemoved { name, .. } => write!(f, "lost the {name} role"),
+        }
+    }
+}
+
+async fn record(pool: impl PgExecutor<'_>, user_id: UserId, timestamp: DateTime<Utc>, event: MemberEvent) -> sqlx::Result<()> {
+    sqlx::query!("INSERT INTO member_history (snowflake, timestamp, event) VALUES ($1, $2, $3)", PgSnowflake(user_id) as _, timestamp, Json(event) as _).execute(pool).await?;
+    Ok(())
+}
+
+/// The recorded Discord membership changes of the given user, oldest first.
+pub(crate) async fn history(pool: impl PgExecutor<'_>, user_id: UserId) -> sqlx::Result<Vec<(DateTime<Utc>, MemberEvent)>> {
+    Ok(sqlx::query!(r#"SELECT timestamp AS "timestamp: DateTime<Utc>", event AS "event: Json<MemberEvent>" FROM member_history WHERE snowflake = $1 ORDER BY timestamp"#, PgSnowflake(user_id) as _)
+        .fetch_all(pool).await?
+        .into_iter()
+        .map(|row| (row.timestamp, row.event.0))
+        .collect())
+}
+
+/// The roles a member gained and lost compared to their Discord data in the people database, with the roles' current names.
+#[derive(Default)]
+pub(crate) struct RoleChanges {
+    pub(crate) added: Vec<(RoleId, String)>,
+    pub(crate) removed: Vec<(RoleId, String)>,
+}
+
+impl RoleChanges {
+    pub(crate) async fn new(ctx: &Context, member: &Member, previous_roles: &[RoleId]) -> serenity::Result<Self> {
+        let added = member.roles.iter().filter(|role| !previous_roles.contains(role)).copied().collect::<Vec<_>>();
+        let removed = previous_roles.iter().filter(|role| !member.roles.contains(role)).copied().collect::<Vec<_>>();
+        if added.is_empty() && removed.is_empty() { return Ok(Self::default()) }
+        let roles = member.guild_id.roles(ctx).await?;
+        let with_name = |role_id| (role_id, roles.get(&role_id).map_or_else(|| format!("deleted role"), |role| role.name.clone()));
+        Ok(Self {
+            added: added.into_iter().map(with_name).collect(),
+            removed: removed.into_iter().map(with_name).collect(),
+        })
+    }
+}
+
+/// Records how the given member differs from the given person's Discord data in the people database. Must be called before that data is updated.
+pub(crate) async fn record_changes(ctx: &Context, member: &Member, person: &User, role_changes: &RoleChanges) -> Result<(), Error> {
+    let data = ctx.data.read().await;
+    let pool = data.get::<DbPool>().expect("missing database connection");
+    if person.discorddata.is_none() {
+        if let Some(joined_at) = member.joined_at {
+            record(pool, member.user.id, *joined_at, MemberEvent::Joined).await?;
+        }
+        return Ok(())
+    }
+    let now = Utc::now();
+    for (role, name) in &role_changes.added {
+        record(pool, member.user.id, now, MemberEvent::RoleAdded { role: *role, name: name.clone() }).await?;
+    }
+    for (role, name) in &role_changes.removed {
+        record(pool, member.user.id, now, MemberEvent::RoleRemoved { role: *role, name: name.clone() }).await?;
+    }
+    Ok(())
+}
+
+/// Records that the given user left the Discord server, and asks the admins to confirm that they're now a former member if applicable.
+pub(crate) async fn record_leave(ctx: &Context, user_id: UserId) -> Result<(), Error> {
+    let data = ctx.data.read().await;
+    let pool = data.get::<DbPool>().expect("missing database connection");
+    let Some(user) = User::from_discord(pool, user_id).await? else { return Ok(()) };
+    record(pool, user_id, Utc::now(), MemberEvent::Left).await?;
+    if user.is_member() {
+        data.get::<Config>().expect("missing config").wurstminebot.main_guild.dev_channel.send_message(ctx, CreateMessage::new()
+            .content(MessageBuilder::default()
+                .mention_user(&user)
+                .push(" left the Discord server. Should they be marked as a former member? This also removes them from the whitelist.")
+                .build()
+            )
+            .components(vec![CreateActionRow::Buttons(vec![
+                CreateButton::new(format!("{FORMER_CUSTOM_ID_PREFIX}{user_id}")).style(ButtonStyle::Danger).label("Mark as former member"),
+            ])])
+        ).await?;
+    }
+    Ok(())
+}
+
+/// Returns the user to mark as a former member if the given custom ID belongs to a confirmation button.
+pub(crate) fn parse_custom_id(custom_id: &str) -> Option<UserId> {
+    custom_id.strip_prefix(FORMER_CUSTOM_ID_PREFIX)?.parse().ok()
+}
+
+/// Marks the given user as a former member on behalf of the given admin, returning the reply to the admin.
+pub(crate) async fn confirm_former(ctx: &Context, admin: &Member, user_id: UserId) -> Result<String, Error> {
+    let guild = crate::discord::main_guild(ctx).await;
+    if admin.guild_id != guild.id || !admin.roles.contains(&guild.admin_role) {
+        return Ok(format!("Only admins can change someone's status."))
+    }
+    {
+        let data = ctx.data.read().await;
+        let pool = data.get::<DbPool>().expect("missing database connection");
+        let Some(mut person) = User::from_discord(pool, user_id).await? else {
+            return Ok(format!("This person is no longer in the people database."))
+        };
+        if !person.is_member() {
+            return Ok(MessageBuilder::default().mention_user(&person).push(" is not a member anymore.").build())
+        }
+        if person.discorddata.is_some() {
+            return Ok(MessageBuilder::default().mention_user(&person).push(" has rejoined the Discord server since leaving.").build())
+        }
+        let by = User::from_discord(pool, admin.user.id).await?.map(|admin| admin.id);
+        person.data.status_history.push(StatusHistoryItem::now(Status::Former, by, Some(format!("left the Discord server"))));
+        person.save_data(pool).await?;
+        guild.dev_channel.say(ctx, MessageBuilder::default()
+            .mention(&admin.user)
+            .push(" changed the status of ")
+            .mention_user(&person)
+            .push(" to ")
+            .push(Status::Former.as_str())
+            .build()
+        ).await?;
+    }
+    crate::whitelist::sync(ctx).await?;
+    Ok(format!("status changed to former member"))
+}
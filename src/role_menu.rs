@@ -0,0 +1,69 @@
+use {
+    itertools::Itertools as _,
+    serenity::{
+        all::{
+            ButtonStyle,
+            CreateActionRow,
+            CreateButton,
+            CreateMessage,
+        },
+        model::prelude::*,
+        prelude::*,
+    },
+    crate::config::{
+        self,
+        Config,
+    },
+};
+
+/// Prefix of the custom IDs of role menu buttons, followed by the role ID.
+///
+/// The role is encoded in the button itself so role menus keep working across bot restarts.
+const CUSTOM_ID_PREFIX: &str = "role_menu:";
+
+/// Discord allows at most 5 rows of 5 buttons per message.
+const MAX_BUTTONS: usize = 25;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Serenity(#[from] serenity::Error),
+    #[error("too many self-assignable roles for a single role menu ({0}, maximum is 25)")]
+    TooManyRoles(usize),
+}
+
+/// Posts a role menu with a toggle button for each of the given guild's self-assignable roles in the given channel.
+pub(crate) async fn post(ctx: &Context, guild: &config::Guild, channel_id: ChannelId) -> Result<(), Error> {
+    if guild.self_assignable_roles.len() > MAX_BUTTONS {
+        return Err(Error::TooManyRoles(guild.self_assignable_roles.len()))
+    }
+    let roles = guild.id.roles(ctx).await?;
+    let buttons = roles.into_values()
+        .filter(|role| guild.self_assignable_roles.contains(&role.id))
+        .sorted_by_key(|role| std::cmp::Reverse(role.position)) // same order as in the member list
+        .map(|role| CreateButton::new(format!("{CUSTOM_ID_PREFIX}{}", role.id)).style(ButtonStyle::Secondary).label(role.name))
+        .collect_vec();
+    let components = buttons.chunks(5).map(|row| CreateActionRow::Buttons(row.to_vec())).collect_vec();
+    channel_id.send_message(ctx, CreateMessage::new()
+        .content("Click a button to add or remove the role:")
+        .components(components)
+    ).await?;
+    Ok(())
+}
+
+/// Returns the role toggled by the button with the given custom ID, if it is a role menu button.
+pub(crate) fn parse_custom_id(custom_id: &str) -> Option<RoleId> {
+    custom_id.strip_prefix(CUSTOM_ID_PREFIX)?.parse().ok()
+}
+
+/// Adds or removes the given role, returning the reply to the member.
+pub(crate) async fn toggle(ctx: &Context, member: &Member, role_id: RoleId) -> serenity::Result<&'static str> {
+    Ok(if !ctx.data.read().await.get::<Config>().expect("missing config").wurstminebot.guild(member.guild_id).is_some_and(|guild| guild.self_assignable_roles.contains(&role_id)) {
+        "this role is no longer self-assignable"
+    } else if member.roles.contains(&role_id) {
+        member.remove_role(ctx, role_id).await?;
+        "role removed"
+    } else {
+        member.add_role(ctx, role_id).await?;
+        "role added"
+    })
+}
@@ -25,7 +25,6 @@ pub(crate) enum Error {
 
 pub(crate) enum VersionSpec {
     Exact(#[allow(unused)] String),
-    LatestRelease,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
@@ -74,6 +73,14 @@ impl World {
         craftping::tokio::ping(&mut stream, &hostname, port).await
     }
 
+    pub(crate) async fn start(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    pub(crate) async fn stop(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
     pub(crate) async fn tellraw(&self, rcpt: &str, msg: &Chat) -> Result<String, Error> {
         Ok(self.command(&format!("tellraw {} {}", rcpt, msg)).await?)
     }
@@ -0,0 +1,223 @@
+use {
+    std::{
+        collections::BTreeSet,
+        path::Path,
+        sync::Mutex,
+        time::Duration,
+    },
+    minecraft::chat::Chat,
+    serenity::{
+        all::{
+            CreateMessage,
+            EditMessage,
+            MessageBuilder,
+        },
+        model::prelude::*,
+        prelude::*,
+    },
+    tokio::{
+        process::Command,
+        time::{
+            sleep,
+            timeout,
+        },
+    },
+    wheel::traits::AsyncCommandOutputExt as _,
+    crate::{
+        BASE_PATH,
+        backup::Backup,
+        discord::{
+            DbPool,
+            HttpClient,
+            online_players,
+        },
+    },
+};
+#[cfg(not(target_os = "linux"))] use crate::systemd_minecraft;
+
+/// Seconds before the update at which online players are warned.
+const COUNTDOWN: [u64; 4] = [60, 30, 10, 5];
+/// How long the server has to respond to pings after an update before it's rolled back.
+const START_TIMEOUT: Duration = Duration::from_mins(5);
+const PING_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Names of the worlds which are currently being updated.
+static IN_PROGRESS: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Backup(#[from] crate::backup::Error),
+    #[error(transparent)] Minecraft(#[from] systemd_minecraft::Error),
+    #[error(transparent)] Serenity(#[from] serenity::Error),
+    #[error(transparent)] Sql(#[from] sqlx::Error),
+    #[error("pre-update backup {0} not found")]
+    MissingBackup(i32),
+}
+
+/// Marks a world as being updated until dropped, so that it isn't updated twice at the same time.
+pub(crate) struct InProgress(String);
+
+impl InProgress {
+    /// Returns `None` if the world is already being updated.
+    pub(crate) fn start(world: &systemd_minecraft::World) -> Option<Self> {
+        let name = world.to_string();
+        IN_PROGRESS.lock().expect("update lock poisoned").insert(name.clone()).then(|| Self(name))
+    }
+}
+
+impl Drop for InProgress {
+    fn drop(&mut self) {
+        IN_PROGRESS.lock().expect("update lock poisoned").remove(&self.0);
+    }
+}
+
+/// A single Discord message which lists the steps of an update as they complete.
+struct Progress {
+    msg: Message,
+    lines: Vec<String>,
+}
+
+impl Progress {
+    async fn new(ctx: &Context, channel_id: ChannelId, title: String) -> serenity::Result<Self> {
+        Ok(Self {
+            msg: channel_id.send_message(ctx, CreateMessage::new().content(&title)).await?,
+            lines: vec![title],
+        })
+    }
+
+    async fn step(&mut self, ctx: &Context, line: String) -> serenity::Result<()> {
+        self.lines.push(line);
+        self.msg.edit(ctx, EditMessage::new().content(self.lines.join("\n"))).await
+    }
+
+    /// Reports an error that ends the update.
+    async fn fail(&mut self, ctx: &Context, what: &str, e: impl std::error::Error) -> serenity::Result<()> {
+        self.step(ctx, MessageBuilder::default().push("❌ ").push(what).push(": ").push_safe(e.to_string()).push(" (").push_mono_safe(format!("{e:?}")).push(")").build()).await
+    }
+}
+
+/// Warns online players about the update, then waits until it's time to start it.
+async fn countdown(world: &systemd_minecraft::World, version: &str) -> Result<(), systemd_minecraft::Error> {
+    for (idx, &secs) in COUNTDOWN.iter().enumerate() {
+        let mut chat = Chat::from(format!("[Server] The server will restart to update to Minecraft {version} in {secs} seconds."));
+        chat.color(minecraft::chat::Color::Gold);
+        world.tellraw("@a", &chat).await?;
+        sleep(Duration::from_secs(secs - COUNTDOWN.get(idx + 1).copied().unwrap_or_default())).await;
+    }
+    Ok(())
+}
+
+/// Waits until the server responds to pings. Returns `false` if it doesn't within [`START_TIMEOUT`].
+async fn wait_for_start(world: &systemd_minecraft::World) -> bool {
+    timeout(START_TIMEOUT, async {
+        while world.ping().await.is_err() {
+            sleep(PING_INTERVAL).await;
+        }
+    }).await.is_ok()
+}
+
+/// Restores the pre-update backup and reinstalls the previous version. The world is only started again if it was running before the update.
+async fn roll_back(ctx: &Context, world: &systemd_minecraft::World, backup_id: i32, previous_version: Option<String>, was_running: bool, requested_by: UserId) -> Result<(), Error> {
+    let backup = {
+        let data = ctx.data.read().await;
+        let pool = data.get::<DbPool>().expect("missing database connection");
+        Backup::from_id(pool, backup_id).await?.ok_or(Error::MissingBackup(backup_id))?
+    };
+    // stop the world first so the restored files aren't loaded by the new version
+    if world.is_running().await? {
+        world.stop().await?;
+    }
+    crate::backup::restore(ctx, &backup, Some(requested_by)).await?;
+    if let Some(previous_version) = previous_version {
+        world.update(systemd_minecraft::VersionSpec::Exact(previous_version)).await?;
+    }
+    if was_running && !world.is_running().await? {
+        world.start().await?;
+    }
+    Ok(())
+}
+
+/// Updates the world to the given version, or the latest release, reporting progress in a message in the given channel.
+///
+/// The world is backed up first, and rolled back to that backup if the update fails or the server doesn't come back up.
+pub(crate) async fn run(ctx: &Context, channel_id: ChannelId, world: &systemd_minecraft::World, requested_by: UserId, version: Option<String>) -> Result<(), Error> {
+    let http_client = ctx.data.read().await.get::<HttpClient>().expect("missing HTTP client").clone();
+    let mut progress = Progress::new(ctx, channel_id, MessageBuilder::default().push("Updating ").push_safe(world.to_string()).push(" world").build()).await?;
+    let manifest = match crate::log::version_manifest(&http_client).await {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            progress.fail(ctx, "failed to load the launcher manifest", e).await?;
+            return Ok(())
+        }
+    };
+    let version = version.unwrap_or(manifest.latest.release);
+    if !manifest.versions.iter().any(|info| info.id == version) {
+        progress.step(ctx, MessageBuilder::default().push("❌ ").push_mono_safe(&version).push(" is not a Minecraft version known to the launcher").build()).await?;
+        return Ok(())
+    }
+    let previous_version = match world.version().await {
+        Ok(previous_version) => previous_version,
+        Err(e) => {
+            progress.fail(ctx, "failed to check the current version", e).await?;
+            return Ok(())
+        }
+    };
+    if previous_version.as_ref().is_some_and(|previous_version| *previous_version == version) {
+        progress.step(ctx, MessageBuilder::default().push("✅ already on ").push_safe(&version).build()).await?;
+        return Ok(())
+    }
+    progress.step(ctx, MessageBuilder::default().push("✅ found ").push_safe(&version).push(" in the launcher manifest").build()).await?;
+    let running = match world.is_running().await {
+        Ok(running) => running,
+        Err(e) => {
+            progress.fail(ctx, "failed to check whether the world is running", e).await?;
+            return Ok(())
+        }
+    };
+    if running {
+        match online_players(world).await {
+            Ok(players) => if players.is_some_and(|players| !players.is_empty()) {
+                progress.step(ctx, format!("⏳ warning online players, updating in {} seconds…", COUNTDOWN[0])).await?;
+                if let Err(e) = countdown(world, &version).await {
+                    progress.fail(ctx, "failed to warn online players, not updating", e).await?;
+                    return Ok(())
+                }
+            },
+            Err(e) => {
+                progress.fail(ctx, "failed to check for online players, not updating", e).await?;
+                return Ok(())
+            }
+        }
+    }
+    let backup_id = match crate::backup::create(ctx, world, Some(requested_by), &format!("before update to {version}")).await {
+        Ok(backup_id) => backup_id,
+        Err(e) => {
+            progress.fail(ctx, "backup failed, not updating", e).await?;
+            return Ok(())
+        }
+    };
+    progress.step(ctx, format!("✅ backed up as backup {backup_id}")).await?;
+    if *world == systemd_minecraft::World::default() {
+        // the main world is also backed up off-site, which the local backups don't replace
+        if let Err(e) = Command::new(Path::new(BASE_PATH).join("bin").join("wurstminebackup")).check("wurstminebackup").await {
+            progress.fail(ctx, "wurstminebackup failed, not updating", e).await?;
+            return Ok(())
+        }
+        progress.step(ctx, format!("✅ ran wurstminebackup")).await?;
+    }
+    let failure = match world.update(systemd_minecraft::VersionSpec::Exact(version.clone())).await {
+        Ok(()) if !running || wait_for_start(world).await => None,
+        Ok(()) => Some(format!("the server didn't come back up within {} minutes", START_TIMEOUT.as_secs() / 60)),
+        Err(e) => Some(format!("update error: {e}")),
+    };
+    if let Some(failure) = failure {
+        progress.step(ctx, MessageBuilder::default().push("❌ ").push_safe(failure).push(format!(", rolling back to backup {backup_id}…")).build()).await?;
+        match roll_back(ctx, world, backup_id, previous_version, running, requested_by).await {
+            Ok(()) => progress.step(ctx, format!("✅ rolled back")).await?,
+            Err(e) => progress.fail(ctx, "rollback failed", e).await?,
+        }
+    } else {
+        progress.step(ctx, MessageBuilder::default().push("✅ updated to ").push_safe(&version).build()).await?;
+    }
+    Ok(())
+}
@@ -23,6 +23,13 @@ use {
         regex_captures,
         regex_is_match,
     },
+    rand::{
+        distr::{
+            Alphanumeric,
+            SampleString as _,
+        },
+        rng,
+    },
     rocket::{
         FromForm,
         State,
@@ -73,10 +80,8 @@ use {
             self,
             Version,
         },
-        discord::{
-            self,
-            PgSnowflake,
-        },
+        config::Config,
+        discord::PgSnowflake,
         form::{
             form_checkbox,
             form_field,
@@ -87,6 +92,7 @@ use {
             Script,
             Tab,
             asset,
+            base_uri,
             page,
         },
         time::{
@@ -173,6 +179,14 @@ impl User {
         )
     }
 
+    /// Adds a Discord user who isn't in the people database yet, e.g. because they're being invited.
+    ///
+    /// The new entry has no Discord data until it's exported by the user list handler.
+    pub(crate) async fn create_from_discord(db_pool: impl PgExecutor<'_>, discord_id: UserId) -> sqlx::Result<()> {
+        sqlx::query!("INSERT INTO people (snowflake, apikey) VALUES ($1, $2)", PgSnowflake(discord_id) as _, Alphanumeric.sample_string(&mut rng(), 25)).execute(db_pool).await?;
+        Ok(())
+    }
+
     pub(crate) async fn from_id(db_pool: impl PgExecutor<'_>, id: Id) -> sqlx::Result<Self> {
         Ok(match id {
             Id::Discord(discord_id) | Id::Both { discord_id, .. } => Self::from_discord(db_pool, discord_id).await?,
@@ -241,6 +255,26 @@ impl User {
         )
     }
 
+    /// Looks up a user by a Minecraft nickname, e.g. from a server log line.
+    ///
+    /// Previous nicknames are also considered, but a user whose current nickname matches takes precedence.
+    pub(crate) async fn from_minecraft_nick(db_pool: impl PgExecutor<'_>, nick: &str) -> sqlx::Result<Option<Self>> {
+        Ok(
+            sqlx::query!(r#"SELECT wmbid, snowflake AS "snowflake: PgSnowflake<UserId>", data AS "data!: Json<Data>", apikey, discorddata AS "discorddata: Json<DiscordData>" FROM people WHERE data -> 'minecraft' -> 'nicks' ? $1 ORDER BY data -> 'minecraft' -> 'nicks' ->> -1 = $1 DESC LIMIT 1"#, nick).fetch_optional(db_pool).await?
+            .map(|row| Self {
+                id: match (row.wmbid, row.snowflake) {
+                    (None, None) => unreachable!("person in database with no Wurstmineberg ID and no Discord snowflake"),
+                    (None, Some(PgSnowflake(discord_id))) => Id::Discord(discord_id),
+                    (Some(wmbid), None) => Id::Wmbid(wmbid),
+                    (Some(wmbid), Some(PgSnowflake(discord_id))) => Id::Both { wmbid, discord_id },
+                },
+                api_key: row.apikey,
+                data: row.data.0,
+                discorddata: row.discorddata.map(|Json(discorddata)| discorddata),
+            })
+        )
+    }
+
     pub(crate) fn wmbid(&self) -> Option<&str> {
         self.id.wmbid()
     }
@@ -253,6 +287,16 @@ impl User {
         format!("/people/{}", self.id.url_part())
     }
 
+    /// An absolute URL to this user's Minecraft player head, for use outside of the website such as in Discord embeds.
+    pub(crate) fn player_head_url(&self) -> Option<String> {
+        self.data.minecraft.uuid.is_some().then(|| uri!(base_uri(), api::player_head(Version::default(), &self.id)).to_string())
+    }
+
+    /// An absolute URL to this user's avatar: their Discord avatar if available, otherwise their Minecraft player head.
+    pub(crate) fn avatar_url(&self) -> Option<String> {
+        self.discorddata.as_ref().and_then(|discorddata| discorddata.avatar.as_ref()).map(|avatar| avatar.to_string()).or_else(|| self.player_head_url())
+    }
+
     pub(crate) fn html_avatar(&self, size: u16) -> RawHtml<String> {
         let (url, pixelate) = if let Some(avatar) = self.discorddata.as_ref().and_then(|discorddata| discorddata.avatar.as_ref()) {
             (avatar.to_string(), false)
@@ -270,6 +314,59 @@ impl User {
         self.data.minecraft.uuid
     }
 
+    /// The user's current status, i.e. the status from the most recent entry in their status history.
+    pub(crate) fn status(&self) -> Option<Status> {
+        self.data.status_history.last().map(|hist| hist.status)
+    }
+
+    /// Whether this user is a founding or later member, as opposed to e.g. a guest or invitee.
+    pub(crate) fn is_member(&self) -> bool {
+        matches!(self.status(), Some(Status::Founding | Status::Later))
+    }
+
+    /// If this user is currently invited, the end of the veto period for their invite.
+    pub(crate) fn veto_deadline(&self) -> Option<DateTime<Utc>> {
+        self.data.status_history.last().filter(|hist| hist.status == Status::Invited).and_then(|hist| hist.deadline)
+    }
+
+    /// Whether this user has the admin role in the main Discord guild. Admins' nicknames can't be changed by the bot.
+    pub(crate) fn is_discord_admin(&self, config: &Config) -> bool {
+        self.discorddata.as_ref().is_some_and(|discorddata| discorddata.roles.contains(&config.wurstminebot.main_guild.admin_role))
+    }
+
+    /// Whether this user should be on the Minecraft whitelist according to their status.
+    pub(crate) fn is_whitelisted(&self) -> bool {
+        matches!(self.status(), Some(Status::Founding | Status::Later | Status::Guest | Status::Invited))
+    }
+
+    /// The user's current Minecraft nickname, if known.
+    pub(crate) fn minecraft_nick(&self) -> Option<&str> {
+        self.data.minecraft.nicks.last().map(|nick| &**nick)
+    }
+
+    /// Whether this user has opted into sending DMs from Discord to Minecraft players and receiving them in Minecraft.
+    pub(crate) fn allows_minecraft_dms(&self) -> bool {
+        self.data.options.get("minecraft_dms").copied().unwrap_or(false)
+    }
+
+    /// Whether this user wants to be told in Minecraft chat when someone joins or leaves a Discord voice channel.
+    pub(crate) fn wants_voice_notifications(&self) -> bool {
+        self.data.options.get("voice_notifications").copied().unwrap_or(false)
+    }
+
+    /// Links the given Minecraft account to this user, recording the nickname if it changed.
+    ///
+    /// If a different account was linked before, its nickname history is discarded.
+    pub(crate) fn set_minecraft(&mut self, uuid: Uuid, nick: String) {
+        if self.data.minecraft.uuid.is_some_and(|prev_uuid| prev_uuid != uuid) {
+            self.data.minecraft.nicks.clear();
+        }
+        self.data.minecraft.uuid = Some(uuid);
+        if self.data.minecraft.nicks.last().is_none_or(|last_nick| *last_nick != nick) {
+            self.data.minecraft.nicks.push(nick);
+        }
+    }
+
     pub(crate) async fn save_data(&self, db_pool: impl PgExecutor<'_>) -> sqlx::Result<()> {
         match self.id {
             Id::Both { discord_id, .. } | Id::Discord(discord_id) => sqlx::query!("UPDATE people SET data = $1 WHERE snowflake = $2", Json(&self.data) as _, PgSnowflake(discord_id) as _),
@@ -425,14 +522,30 @@ pub(crate) struct StatusHistoryItem {
     by: Option<Id>,
     #[serde(skip_serializing_if = "Option::is_none")]
     date: Option<DateWithOptionalTime>,
+    /// For invites, the end of the veto period.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) deadline: Option<DateTime<Utc>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     reason: Option<String>,
     status: Status,
 }
 
+impl StatusHistoryItem {
+    /// A status change happening now.
+    pub(crate) fn now(status: Status, by: Option<Id>, reason: Option<String>) -> Self {
+        Self {
+            by,
+            date: Some(DateWithOptionalTime::DateTime(Utc::now())),
+            deadline: None,
+            reason,
+            status,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
-enum Status {
+pub(crate) enum Status {
     Founding,
     Later,
     Former,
@@ -442,7 +555,7 @@ enum Status {
 }
 
 impl Status {
-    fn as_str(&self) -> &'static str {
+    pub(crate) fn as_str(&self) -> &'static str {
         match self {
             Self::Founding => "founding",
             Self::Later => "later",
@@ -589,6 +702,20 @@ pub(crate) async fn profile(db_pool: &State<PgPool>, me: Option<User>, uri: Orig
     }
 
     let Some(user) = user.parse(&**db_pool).await? else { return Ok(None) };
+    let mut timeline = user.data.status_history.iter()
+        .filter_map(|item| Some((item.date?, match item.status {
+            Status::Founding => format!("became a founding member"),
+            Status::Later => format!("became a member"),
+            Status::Former => format!("became a former member"),
+            Status::Vetoed => format!("invite was vetoed"),
+            Status::Guest => format!("became a guest"),
+            Status::Invited => format!("was invited"),
+        })))
+        .collect::<Vec<_>>();
+    if let Some(discord_id) = user.discord_id() {
+        timeline.extend(crate::member::history(&**db_pool, discord_id).await?.into_iter().map(|(timestamp, event)| (DateWithOptionalTime::DateTime(timestamp), event.to_string())));
+    }
+    timeline.sort_by_key(|(date, _)| date.sort_key());
     Ok(Some(page(&me, &uri, PageStyle { extra_scripts: vec![
         Script::External(format!("https://raw.githubusercontent.com/alexei/sprintf.js/master/dist/sprintf.min.js")), //TODO this doesn't load properly, remove dependency or vendor
         Script::External(asset("/js/profile.js")),
@@ -696,6 +823,24 @@ pub(crate) async fn profile(db_pool: &State<PgPool>, me: Option<User>, uri: Orig
                 }
             }
         }
+        @if !timeline.is_empty() {
+            h2 : "Membership";
+            table(class = "table table-responsive") {
+                tbody {
+                    @for (date, description) in timeline {
+                        tr {
+                            td {
+                                @match date {
+                                    DateWithOptionalTime::DateTime(date) => : format_date(date);
+                                    DateWithOptionalTime::Date(date) => : format_date_naive(date);
+                                }
+                            }
+                            td : description;
+                        }
+                    }
+                }
+            }
+        }
         h2 : "Statistics";
         ul(id = "pagination", class = "nav nav-tabs") {
             li {
@@ -951,7 +1096,7 @@ impl<'v> PreferencesFormDefaults<'v> {
     }
 }
 
-fn preferences_form(me: User, uri: Origin<'_>, csrf: Option<&CsrfToken>, saved: bool, tab: &str, defaults: PreferencesFormDefaults<'_>) -> RawHtml<String> {
+fn preferences_form(config: &Config, me: User, uri: Origin<'_>, csrf: Option<&CsrfToken>, saved: bool, tab: &str, defaults: PreferencesFormDefaults<'_>) -> RawHtml<String> {
     let (mut profile_errors, mut settings_errors) = match tab {
         "profile" => (defaults.errors(), Vec::default()),
         "settings" => (Vec::default(), defaults.errors()),
@@ -995,7 +1140,7 @@ fn preferences_form(me: User, uri: Origin<'_>, csrf: Option<&CsrfToken>, saved:
                                 p(class = "col-sm-10 col-sm-offset-2 preferences-profile-lead") : "These preferences control how you are displayed to other people visiting the site.";
                             }
                             : full_form(uri!(profile_post), csrf, html! {
-                                @if !me.discorddata.as_ref().is_some_and(|discorddata| discorddata.roles.contains(&discord::ADMIN)) {
+                                @if !me.is_discord_admin(config) {
                                     : form_field("name", &mut profile_errors, "Name", html! {
                                         input(class = "form-control", type = "text", name = "name", value = defaults.name());
                                     }, Some(html! {
@@ -1049,12 +1194,18 @@ fn preferences_form(me: User, uri: Origin<'_>, csrf: Option<&CsrfToken>, saved:
                                     : "When this option is on, the bot will send you a tweet after a random time (between 1 and 6 months) of inactivity (this feature is not yet implemented, see here for the feature request) and on your whitelisting anniversary (not yet implemented either, see here for the feature request). When it's off, it will still tweet about your anniversary, but without @mentioning you.";
                                 }));
                                 */
+                                : form_checkbox("minecraft_dms", &mut settings_errors, "Minecraft DMs", defaults.option("minecraft_dms", false), Some(html! {
+                                    : "When this option is on, you can DM the bot on Discord with a person's name followed by your message to send it to them while they're playing Minecraft, and others can do the same for you. Both sides need to have this option enabled.";
+                                }));
                                 : form_checkbox("public_info", &mut settings_errors, "User data is public", defaults.option("public_info", true), Some(html! {
                                     : "When this option is off, only server members logged in on the website can view your profile page and statistics. Note that your data is still publicly accessible via the API.";
                                 }));
                                 : form_checkbox("show_inventory", &mut settings_errors, "Show inventory", defaults.option("show_inventory", false), Some(html! {
                                     : "Whether or not your profile page should show your inventory and Ender chest content.";
                                 }));
+                                : form_checkbox("voice_notifications", &mut settings_errors, "Voice channel notifications", defaults.option("voice_notifications", false), Some(html! {
+                                    : "When this option is on, the bot will tell you in Minecraft chat when someone joins, leaves, or switches voice channels on our Discord server.";
+                                }));
                                 @let timezones = {
                                     let mut timezones = chrono_tz::TZ_VARIANTS;
                                     timezones.sort_by_key(|tz| Reverse(matches!(*tz, Etc::UTC | Europe::Berlin | Europe::Vienna)));
@@ -1077,8 +1228,8 @@ fn preferences_form(me: User, uri: Origin<'_>, csrf: Option<&CsrfToken>, saved:
 }
 
 #[rocket::get("/preferences?<tab>")]
-pub(crate) fn preferences_get(me: User, uri: Origin<'_>, csrf: Option<CsrfToken>, tab: Option<&str>) -> RawHtml<String> {
-    preferences_form(me.clone(), uri, csrf.as_ref(), false, tab.unwrap_or("profile"), PreferencesFormDefaults::Values(me))
+pub(crate) fn preferences_get(config: &State<Config>, me: User, uri: Origin<'_>, csrf: Option<CsrfToken>, tab: Option<&str>) -> RawHtml<String> {
+    preferences_form(config, me.clone(), uri, csrf.as_ref(), false, tab.unwrap_or("profile"), PreferencesFormDefaults::Values(me))
 }
 
 #[derive(FromForm, CsrfForm)]
@@ -1095,11 +1246,11 @@ pub(crate) struct ProfileForm {
 }
 
 #[rocket::post("/preferences?tab=profile", data = "<form>", rank = 0)]
-pub(crate) async fn profile_post(db_pool: &State<PgPool>, discord_ctx: &State<RwFuture<DiscordCtx>>, mut me: User, uri: Origin<'_>, csrf: Option<CsrfToken>, form: Form<Contextual<'_, ProfileForm>>) -> Result<RawHtml<String>, Error> {
+pub(crate) async fn profile_post(db_pool: &State<PgPool>, config: &State<Config>, discord_ctx: &State<RwFuture<DiscordCtx>>, mut me: User, uri: Origin<'_>, csrf: Option<CsrfToken>, form: Form<Contextual<'_, ProfileForm>>) -> Result<RawHtml<String>, Error> {
     let mut form = form.into_inner();
     form.verify(&csrf);
     Ok(if let Some(ref value) = form.value {
-        if !me.discorddata.as_ref().is_some_and(|discorddata| discorddata.roles.contains(&discord::ADMIN)) && !regex_is_match!("^[^@#:]{2,32}$", &value.name) { //TODO better compliance with https://discord.com/developers/docs/resources/user
+        if !me.is_discord_admin(config) && !regex_is_match!("^[^@#:]{2,32}$", &value.name) { //TODO better compliance with https://discord.com/developers/docs/resources/user
             form.context.push_error(form::Error::validation("Discord display names must be 2–32 characters and must not contain ^@#:").with_name("name"));
         }
         if value.description.len() > 1000 {
@@ -1129,14 +1280,14 @@ pub(crate) async fn profile_post(db_pool: &State<PgPool>, discord_ctx: &State<Rw
             None
         };
         if form.context.errors().next().is_some() {
-            preferences_form(me, uri, csrf.as_ref(), false, "profile", PreferencesFormDefaults::Context(form.context))
+            preferences_form(config, me, uri, csrf.as_ref(), false, "profile", PreferencesFormDefaults::Context(form.context))
         } else {
-            if let (Some(discord_id), Some(discorddata)) = (me.discord_id(), &me.discorddata) {
-                if !discorddata.roles.contains(&discord::ADMIN) {
-                    discord::GUILD.edit_member(&*discord_ctx.read().await, discord_id, EditMember::default().nickname(&value.name)).await?;
+            if let (Some(discord_id), Some(_)) = (me.discord_id(), &me.discorddata) {
+                if !me.is_discord_admin(config) {
+                    config.wurstminebot.main_guild.id.edit_member(&*discord_ctx.read().await, discord_id, EditMember::default().nickname(&value.name)).await?;
                 }
             }
-            if !me.discorddata.as_ref().is_some_and(|discorddata| discorddata.roles.contains(&discord::ADMIN)) {
+            if !me.is_discord_admin(config) {
                 me.data.name = Some(value.name.clone());
             }
             me.data.description = (!value.description.is_empty()).then(|| value.description.clone());
@@ -1145,10 +1296,10 @@ pub(crate) async fn profile_post(db_pool: &State<PgPool>, discord_ctx: &State<Rw
             me.data.website = (!value.website.is_empty()).then(|| value.website.parse().expect("validated"));
             me.data.fav_color = fav_color;
             me.save_data(&**db_pool).await?;
-            preferences_form(me.clone(), uri, csrf.as_ref(), true, "profile", PreferencesFormDefaults::Values(me))
+            preferences_form(config, me.clone(), uri, csrf.as_ref(), true, "profile", PreferencesFormDefaults::Values(me))
         }
     } else {
-        preferences_form(me, uri, csrf.as_ref(), false, "profile", PreferencesFormDefaults::Context(form.context))
+        preferences_form(config, me, uri, csrf.as_ref(), false, "profile", PreferencesFormDefaults::Context(form.context))
     })
 }
 
@@ -1159,13 +1310,15 @@ pub(crate) struct SettingsForm {
     allow_online_notifications: bool,
     //activity_tweets: bool,
     //inactivity_tweets: bool,
+    minecraft_dms: bool,
     public_info: bool,
     show_inventory: bool,
+    voice_notifications: bool,
     timezone: String,
 }
 
 #[rocket::post("/preferences?tab=settings", data = "<form>", rank = 1)]
-pub(crate) async fn settings_post(db_pool: &State<PgPool>, mut me: User, uri: Origin<'_>, csrf: Option<CsrfToken>, form: Form<Contextual<'_, SettingsForm>>) -> Result<RawHtml<String>, rocket_util::Error<sqlx::Error>> {
+pub(crate) async fn settings_post(db_pool: &State<PgPool>, config: &State<Config>, mut me: User, uri: Origin<'_>, csrf: Option<CsrfToken>, form: Form<Contextual<'_, SettingsForm>>) -> Result<RawHtml<String>, rocket_util::Error<sqlx::Error>> {
     let mut form = form.into_inner();
     form.verify(&csrf);
     Ok(if let Some(ref value) = form.value {
@@ -1173,21 +1326,23 @@ pub(crate) async fn settings_post(db_pool: &State<PgPool>, mut me: User, uri: Or
             form.context.push_error(form::Error::validation("Not a valid timezone").with_name("timezone"));
         }
         if form.context.errors().next().is_some() {
-            preferences_form(me, uri, csrf.as_ref(), false, "settings", PreferencesFormDefaults::Context(form.context))
+            preferences_form(config, me, uri, csrf.as_ref(), false, "settings", PreferencesFormDefaults::Context(form.context))
         } else {
             me.data.options.insert(format!("allow_online_notifications"), value.allow_online_notifications);
             //me.data.options.insert(format!("activity_tweets"), value.activity_tweets);
             //me.data.options.insert(format!("inactivity_tweets"), value.inactivity_tweets);
+            me.data.options.insert(format!("minecraft_dms"), value.minecraft_dms);
             me.data.options.insert(format!("public_info"), value.public_info);
             me.data.options.insert(format!("show_inventory"), value.show_inventory);
+            me.data.options.insert(format!("voice_notifications"), value.voice_notifications);
             me.data.timezone = (!value.timezone.is_empty()).then(|| value.timezone.parse().expect("validated"));
             match me.id {
                 Id::Both { discord_id, .. } | Id::Discord(discord_id) => sqlx::query!("UPDATE people SET data = $1 WHERE snowflake = $2", Json(&me.data) as _, PgSnowflake(discord_id) as _),
                 Id::Wmbid(ref wmbid) => sqlx::query!("UPDATE people SET data = $1 WHERE wmbid = $2", Json(&me.data) as _, wmbid),
             }.execute(&**db_pool).await?;
-            preferences_form(me.clone(), uri, csrf.as_ref(), true, "settings", PreferencesFormDefaults::Values(me))
+            preferences_form(config, me.clone(), uri, csrf.as_ref(), true, "settings", PreferencesFormDefaults::Values(me))
         }
     } else {
-        preferences_form(me, uri, csrf.as_ref(), false, "settings", PreferencesFormDefaults::Context(form.context))
+        preferences_form(config, me, uri, csrf.as_ref(), false, "settings", PreferencesFormDefaults::Context(form.context))
     })
 }
@@ -0,0 +1,125 @@
+use {
+    std::collections::HashMap,
+    minecraft::chat::Chat,
+    serenity::{
+        model::prelude::*,
+        prelude::*,
+    },
+    serenity_utils::handler::voice_state::VoiceStates,
+    tokio::sync::watch,
+    wurstmineberg_web::websocket::{
+        VoiceChannel,
+        VoiceMember,
+    },
+    crate::{
+        discord::{
+            DbPool,
+            online_players,
+        },
+        user::User,
+    },
+};
+#[cfg(not(target_os = "linux"))] use crate::systemd_minecraft;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Minecraft(#[from] systemd_minecraft::Error),
+    #[error(transparent)] Sql(#[from] sqlx::Error),
+}
+
+/// The current state of the Discord server's voice channels, or `None` if it hasn't been received from Discord yet.
+///
+/// Subscribe to the sender to be notified of changes.
+pub(crate) enum VoiceState {}
+
+impl TypeMapKey for VoiceState {
+    type Value = watch::Sender<Option<Vec<VoiceChannel>>>;
+}
+
+/// Someone joining, leaving, or switching voice channels.
+enum Change<'a> {
+    Joined(&'a VoiceMember, &'a str),
+    Left(&'a VoiceMember, &'a str),
+    Moved(&'a VoiceMember, &'a str, &'a str),
+}
+
+impl Change<'_> {
+    fn member(&self) -> &VoiceMember {
+        match self {
+            Self::Joined(member, _) | Self::Left(member, _) | Self::Moved(member, _, _) => member,
+        }
+    }
+
+    fn chat(&self) -> Chat {
+        let mut chat = Chat::from(format!("[Discord] "));
+        chat.color(minecraft::chat::Color::Aqua);
+        chat.add_extra(&*self.member().username);
+        chat.add_extra(match self {
+            Self::Joined(_, channel) => format!(" joined voice channel {channel}"),
+            Self::Left(_, channel) => format!(" left voice channel {channel}"),
+            Self::Moved(_, from, to) => format!(" moved from voice channel {from} to {to}"),
+        });
+        chat
+    }
+}
+
+fn changes<'a>(old: &'a [VoiceChannel], new: &'a [VoiceChannel]) -> Vec<Change<'a>> {
+    fn by_member(channels: &[VoiceChannel]) -> HashMap<UserId, (&VoiceMember, &str)> {
+        channels.iter()
+            .flat_map(|channel| channel.members.iter().map(|member| (member.id, (member, &*channel.name))))
+            .collect()
+    }
+
+    let old = by_member(old);
+    let new = by_member(new);
+    let mut changes = Vec::default();
+    for (id, &(member, channel)) in &new {
+        match old.get(id) {
+            None => changes.push(Change::Joined(member, channel)),
+            Some(&(_, old_channel)) if old_channel != channel => changes.push(Change::Moved(member, old_channel, channel)),
+            Some(_) => {}
+        }
+    }
+    for (id, &(member, channel)) in &old {
+        if !new.contains_key(id) {
+            changes.push(Change::Left(member, channel));
+        }
+    }
+    changes
+}
+
+/// Converts the voice state reported by Discord into the format of the WebSocket API.
+pub(crate) fn channels(VoiceStates(voice_states): &VoiceStates) -> Vec<VoiceChannel> {
+    voice_states.iter()
+        .map(|(&channel_id, (channel_name, members))| VoiceChannel {
+            id: channel_id,
+            name: channel_name.clone(),
+            members: members.iter().map(|user| VoiceMember {
+                id: user.id,
+                username: user.name.clone(),
+            }).collect(),
+        })
+        .collect()
+}
+
+/// Publishes the new voice state and announces any joins and leaves in game to players who opted in.
+pub(crate) async fn update(ctx: &Context, channels: Vec<VoiceChannel>) -> Result<(), Error> {
+    let data = ctx.data.read().await;
+    let Some(old) = data.get::<VoiceState>().expect("missing voice state").send_replace(Some(channels.clone())) else {
+        return Ok(()) // first voice state after startup, don't announce everyone who was already in voice
+    };
+    let changes = changes(&old, &channels);
+    if changes.is_empty() { return Ok(()) }
+    let pool = data.get::<DbPool>().expect("missing database connection");
+    for world in systemd_minecraft::World::all_running().await? {
+        let Some(players) = online_players(&world).await? else { continue };
+        for (name, uuid) in players {
+            let Some(user) = User::from_minecraft_uuid(pool, uuid).await?.filter(|user| user.wants_voice_notifications()) else { continue };
+            for change in &changes {
+                if user.discord_id() == Some(change.member().id) { continue } // they know
+                world.tellraw(&name, &change.chat()).await?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -82,6 +82,24 @@ pub enum ServerMessageV4 {
         cz: i32,
         data: Vec<BlockEntity>,
     },
+    /// The current state of the Discord server's voice channels.
+    /// Sent after [`ClientMessage::SubscribeToVoiceState`] and whenever someone joins, leaves, or switches voice channels.
+    VoiceState {
+        channels: Vec<VoiceChannel>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Protocol)]
+pub struct VoiceChannel {
+    pub id: ChannelId,
+    pub name: String,
+    pub members: Vec<VoiceMember>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Protocol)]
+pub struct VoiceMember {
+    pub id: UserId,
+    pub username: String,
 }
 
 #[derive(Debug, Protocol)]
@@ -113,6 +131,9 @@ pub enum ClientMessage {
         /// The chunk z coordinate, equivalent to the block z coordinates of the blocks in the chunk divided by 16
         cz: i32,
     },
+    /// Request to receive the current state of the Discord server's voice channels, and also receive updates whenever it changes.
+    /// Requires an API key and version 4 or later of the API.
+    SubscribeToVoiceState,
 }
 
 #[derive(Debug, Clone, Protocol)]
@@ -0,0 +1,113 @@
+use {
+    std::collections::{
+        BTreeMap,
+        HashSet,
+    },
+    futures::stream::TryStreamExt as _,
+    itertools::Itertools as _,
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    serenity::{
+        all::MessageBuilder,
+        prelude::*,
+    },
+    uuid::Uuid,
+    wheel::fs,
+    crate::{
+        discord::{
+            DbPool,
+            main_guild,
+        },
+        user::User,
+    },
+};
+#[cfg(not(target_os = "linux"))] use crate::systemd_minecraft;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)] Json(#[from] serde_json::Error),
+    #[error(transparent)] Minecraft(#[from] systemd_minecraft::Error),
+    #[error(transparent)] Serenity(#[from] serenity::Error),
+    #[error(transparent)] Sql(#[from] sqlx::Error),
+    #[error(transparent)] Wheel(#[from] wheel::Error),
+}
+
+/// An entry in a world's `whitelist.json`.
+#[derive(Deserialize, Serialize)]
+struct Entry {
+    uuid: Uuid,
+    name: String,
+}
+
+/// The whitelist state according to the people database.
+#[derive(Default)]
+struct Expected {
+    /// The Minecraft accounts of everyone who should be on the whitelist, with their nicknames.
+    whitelisted: BTreeMap<Uuid, String>,
+    /// The Minecraft accounts of people in the database who should not be on the whitelist.
+    ///
+    /// Whitelist entries not in either set, e.g. ones added manually by server admins, are left alone.
+    not_whitelisted: HashSet<Uuid>,
+}
+
+async fn expected(ctx: &Context) -> sqlx::Result<Expected> {
+    let data = ctx.data.read().await;
+    let pool = data.get::<DbPool>().expect("missing database connection");
+    let mut expected = Expected::default();
+    let mut people = User::all(pool);
+    while let Some(person) = people.try_next().await? {
+        if let Some(uuid) = person.minecraft_uuid() {
+            if person.is_whitelisted() {
+                expected.whitelisted.insert(uuid, person.minecraft_nick().map_or_else(|| uuid.to_string(), str::to_owned));
+            } else {
+                expected.not_whitelisted.insert(uuid);
+            }
+        }
+    }
+    Ok(expected)
+}
+
+/// Adds and removes players on the whitelists of all running worlds to match the people database, and reports any changes in the dev channel.
+///
+/// Players are identified by UUID, so name changes don't cause players to be removed.
+pub(crate) async fn sync(ctx: &Context) -> Result<(), Error> {
+    let expected = expected(ctx).await?;
+    for world in systemd_minecraft::World::all_running().await? {
+        let path = world.dir().join("whitelist.json");
+        let mut entries = fs::read_json::<Vec<Entry>>(&path).await?;
+        let mut removed = Vec::default();
+        entries.retain(|entry| if expected.not_whitelisted.contains(&entry.uuid) {
+            removed.push(entry.name.clone());
+            false
+        } else {
+            true
+        });
+        let mut added = Vec::default();
+        for (&uuid, nick) in &expected.whitelisted {
+            if !entries.iter().any(|entry| entry.uuid == uuid) {
+                entries.push(Entry { uuid, name: nick.clone() });
+                added.push(nick.clone());
+            }
+        }
+        if !added.is_empty() || !removed.is_empty() {
+            fs::write(&path, serde_json::to_vec_pretty(&entries)?).await?;
+            world.command("whitelist reload").await?;
+            let mut builder = MessageBuilder::default();
+            builder.push("whitelist of ");
+            builder.push_safe(world.to_string());
+            builder.push(" synced with the people database:");
+            if !added.is_empty() {
+                builder.push(" added ");
+                builder.push_mono_safe(added.into_iter().join(", "));
+            }
+            if !removed.is_empty() {
+                builder.push(" removed ");
+                builder.push_mono_safe(removed.into_iter().join(", "));
+            }
+            main_guild(ctx).await.dev_channel.say(ctx, builder.build()).await?;
+        }
+    }
+    Ok(())
+}